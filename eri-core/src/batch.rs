@@ -0,0 +1,402 @@
+use crate::certificate::{Certificate, checked_domain, domain_separator, typed_data, typed_digest};
+use crate::error::CertificateError;
+use crate::signing::parse_signature;
+use crate::typed_struct::TypedStruct;
+use ethers_core::types::transaction::eip712::{EIP712Domain, Eip712, Eip712Error, TypedData};
+use ethers_core::types::{Address, H256};
+use ethers_core::utils::keccak256;
+use ethers_signers::Signer;
+use serde::{Deserialize, Serialize};
+
+// A production lot signed at once: the manufacturer signs the Merkle root over the
+// lot's certificates instead of each certificate, and every certificate carries its
+// inclusion proof
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct CertificateBatch {
+    pub root: H256,
+    /// Manufacturer's own label for the lot, e.g. a production run number
+    pub lot: String,
+    pub count: u64,
+    pub owner: Address,
+    pub date: u64,
+    #[serde(skip)]
+    pub domain: EIP712Domain,
+}
+
+crate::typed_struct!(CertificateBatch, "CertificateBatch", {
+    root: H256 => ("root", "bytes32"),
+    lot: String => ("lot", "string"),
+    count: u64 => ("count", "uint64"),
+    owner: Address => ("owner", "address"),
+    date: u64 => ("date", "uint64"),
+});
+
+impl CertificateBatch {
+    /// Builds the tree over `certificates`, which must all be owned by `owner`
+    pub fn new(
+        lot: String,
+        date: u64,
+        owner: Address,
+        certificates: &[Certificate],
+    ) -> Result<(CertificateBatch, MerkleTree), CertificateError> {
+        if let Some(other) = certificates.iter().find(|c| c.owner != owner) {
+            return Err(CertificateError::SignerNotOwner {
+                signer: owner,
+                owner: other.owner,
+            });
+        }
+
+        let tree = MerkleTree::new(certificates.iter().map(merkle_leaf).collect())?;
+        let batch = CertificateBatch {
+            root: tree.root(),
+            lot,
+            count: certificates.len() as u64,
+            owner,
+            date,
+            domain: EIP712Domain::default(),
+        };
+
+        Ok((batch, tree))
+    }
+
+    pub fn with_domain(mut self, domain: EIP712Domain) -> Self {
+        self.domain = domain;
+        self
+    }
+
+    pub fn digest(&self) -> Result<H256, Eip712Error> {
+        self.encode_eip712().map(H256::from)
+    }
+
+    /// The full `eth_signTypedData_v4` payload, for wallets and remote signers
+    pub fn typed_data(&self) -> Result<TypedData, Eip712Error> {
+        typed_data(self, self.domain()?)
+    }
+
+    pub async fn sign<S: Signer>(
+        self,
+        signer: &S,
+    ) -> Result<SignedCertificateBatch, CertificateError> {
+        let signature = signer
+            .sign_typed_data(&self)
+            .await
+            .map_err(|e| CertificateError::Signing(e.to_string()))?;
+
+        Ok(SignedCertificateBatch {
+            batch: self,
+            signature: format!("0x{}", signature),
+        })
+    }
+}
+
+impl Eip712 for CertificateBatch {
+    type Error = Eip712Error;
+
+    fn domain_separator(&self) -> Result<[u8; 32], Self::Error> {
+        Ok(domain_separator(&self.domain()?))
+    }
+
+    fn domain(&self) -> Result<EIP712Domain, Self::Error> {
+        checked_domain(&self.domain)
+    }
+
+    fn type_hash() -> Result<[u8; 32], Self::Error> {
+        Ok(Self::hash_type())
+    }
+
+    fn struct_hash(&self) -> Result<[u8; 32], Self::Error> {
+        Ok(self.hash_struct())
+    }
+
+    fn encode_eip712(&self) -> Result<[u8; 32], Self::Error> {
+        Ok(typed_digest(self.domain_separator()?, self.struct_hash()?))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct SignedCertificateBatch {
+    #[serde(flatten)]
+    pub batch: CertificateBatch,
+    pub signature: String,
+}
+
+// keccak256 of the Certificate struct hash. Hashing twice keeps a leaf (32 byte preimage)
+// from ever being passed off as an inner node (64 byte preimage)
+pub fn merkle_leaf(certificate: &Certificate) -> H256 {
+    H256::from(keccak256(certificate.hash_struct()))
+}
+
+// pairs are sorted before hashing, as in OpenZeppelin's MerkleProof, so a proof is
+// just the sibling hashes with no left/right flags
+fn hash_pair(a: H256, b: H256) -> H256 {
+    let (low, high) = if a <= b { (a, b) } else { (b, a) };
+    let mut bytes = [0u8; 64];
+    bytes[..32].copy_from_slice(low.as_bytes());
+    bytes[32..].copy_from_slice(high.as_bytes());
+    H256::from(keccak256(bytes))
+}
+
+// Binary Merkle tree, an unpaired node is carried up to the next level as is
+#[derive(Clone, Debug)]
+pub struct MerkleTree {
+    // levels[0] holds the leaves, the last level the root
+    levels: Vec<Vec<H256>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<H256>) -> Result<Self, CertificateError> {
+        if leaves.is_empty() {
+            return Err(CertificateError::EmptyBatch);
+        }
+
+        let mut levels = vec![leaves];
+        while let Some(level) = levels.last().filter(|level| level.len() > 1) {
+            let next = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => hash_pair(*a, *b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+
+        Ok(Self { levels })
+    }
+
+    pub fn root(&self) -> H256 {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    /// Sibling hashes from the leaf at `index` up to the root
+    pub fn proof(&self, index: usize) -> Vec<H256> {
+        let mut proof = Vec::new();
+        let mut index = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        proof
+    }
+}
+
+// The root `proof` leads to from `leaf`
+pub fn process_proof(leaf: H256, proof: &[H256]) -> H256 {
+    proof
+        .iter()
+        .fold(leaf, |node, sibling| hash_pair(node, *sibling))
+}
+
+// Checks `certificate` is part of the signed batch and that the batch was signed by the
+// certificate's owner. The certificate's domain is used for the batch signature too.
+// Returns the signer
+pub fn verify_batched(
+    certificate: &Certificate,
+    proof: &[H256],
+    signed: &SignedCertificateBatch,
+) -> Result<Address, CertificateError> {
+    let root = process_proof(merkle_leaf(certificate), proof);
+    if root != signed.batch.root {
+        return Err(CertificateError::NotInBatch {
+            root: signed.batch.root,
+        });
+    }
+
+    let batch = signed.batch.clone().with_domain(certificate.domain.clone());
+    let signature = parse_signature(&signed.signature)?;
+    let signer = signature.recover(batch.digest()?)?;
+
+    // the lot's owner and the certificate's owner must both be the signer
+    if signer != batch.owner || signer != certificate.owner {
+        return Err(CertificateError::SignerNotOwner {
+            signer,
+            owner: certificate.owner,
+        });
+    }
+
+    Ok(signer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certificate::certificate_domain;
+    use ethers_signers::LocalWallet;
+
+    fn wallet(key: &str) -> LocalWallet {
+        key.parse().unwrap()
+    }
+
+    fn manufacturer() -> LocalWallet {
+        wallet("59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d")
+    }
+
+    fn domain() -> EIP712Domain {
+        certificate_domain("ERI", "1", 31337, Address::repeat_byte(0x11))
+    }
+
+    fn certificates(count: usize) -> Vec<Certificate> {
+        (0..count)
+            .map(|i| {
+                Certificate::new(
+                    "Phone".to_string(),
+                    format!("IMEI{}", i),
+                    format!("SN{}", i),
+                    1_700_000_000,
+                    manufacturer().address(),
+                    vec!["red".to_string()],
+                )
+                .with_domain(domain())
+            })
+            .collect()
+    }
+
+    fn leaves(count: usize) -> Vec<H256> {
+        (0..count as u64).map(H256::from_low_u64_be).collect()
+    }
+
+    fn signed_batch(signer: &LocalWallet, certificates: &[Certificate]) -> SignedCertificateBatch {
+        let (batch, _) = CertificateBatch::new(
+            "LOT-1".to_string(),
+            1_700_000_000,
+            signer.address(),
+            certificates,
+        )
+        .unwrap();
+        let batch = batch.with_domain(domain());
+        let signature = signer.sign_hash(batch.digest().unwrap()).unwrap();
+
+        SignedCertificateBatch {
+            batch,
+            signature: format!("0x{}", signature),
+        }
+    }
+
+    #[test]
+    fn single_leaf_is_the_root() {
+        let tree = MerkleTree::new(leaves(1)).unwrap();
+
+        assert_eq!(tree.root(), H256::from_low_u64_be(0));
+        assert!(tree.proof(0).is_empty());
+        assert!(MerkleTree::new(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn two_leaves_hash_to_the_sorted_pair() {
+        let tree = MerkleTree::new(leaves(2)).unwrap();
+
+        assert_eq!(tree.root(), hash_pair(leaves(2)[0], leaves(2)[1]));
+        assert_eq!(tree.root(), hash_pair(leaves(2)[1], leaves(2)[0]));
+        assert_eq!(tree.proof(0), vec![leaves(2)[1]]);
+        assert_eq!(tree.proof(1), vec![leaves(2)[0]]);
+    }
+
+    #[test]
+    fn odd_leaf_is_carried_up() {
+        let leaves = leaves(3);
+        let tree = MerkleTree::new(leaves.clone()).unwrap();
+
+        assert_eq!(
+            tree.root(),
+            hash_pair(hash_pair(leaves[0], leaves[1]), leaves[2])
+        );
+        assert_eq!(tree.proof(2), vec![hash_pair(leaves[0], leaves[1])]);
+    }
+
+    #[test]
+    fn every_proof_leads_to_the_root() {
+        for count in 1..=9 {
+            let leaves = leaves(count);
+            let tree = MerkleTree::new(leaves.clone()).unwrap();
+            assert_eq!(tree.len(), count);
+
+            for (index, leaf) in leaves.iter().enumerate() {
+                assert_eq!(process_proof(*leaf, &tree.proof(index)), tree.root());
+            }
+        }
+    }
+
+    #[test]
+    fn tampered_leaf_or_sibling_misses_the_root() {
+        let leaves = leaves(5);
+        let tree = MerkleTree::new(leaves.clone()).unwrap();
+        let proof = tree.proof(1);
+
+        assert_ne!(process_proof(H256::repeat_byte(0xaa), &proof), tree.root());
+
+        let mut tampered = proof.clone();
+        tampered[0] = H256::repeat_byte(0xaa);
+        assert_ne!(process_proof(leaves[1], &tampered), tree.root());
+    }
+
+    #[test]
+    fn batched_certificate_verifies_against_the_signed_root() {
+        let certificates = certificates(5);
+        let signed = signed_batch(&manufacturer(), &certificates);
+        let tree = MerkleTree::new(certificates.iter().map(merkle_leaf).collect()).unwrap();
+
+        for (index, certificate) in certificates.iter().enumerate() {
+            let signer = verify_batched(certificate, &tree.proof(index), &signed).unwrap();
+            assert_eq!(signer, manufacturer().address());
+        }
+    }
+
+    #[test]
+    fn wrong_root_is_rejected() {
+        let certificates = certificates(3);
+        let mut signed = signed_batch(&manufacturer(), &certificates);
+        let tree = MerkleTree::new(certificates.iter().map(merkle_leaf).collect()).unwrap();
+        signed.batch.root = H256::repeat_byte(0xaa);
+
+        assert!(matches!(
+            verify_batched(&certificates[0], &tree.proof(0), &signed),
+            Err(CertificateError::NotInBatch { .. })
+        ));
+    }
+
+    #[test]
+    fn batch_signed_by_another_key_is_rejected() {
+        let certificates = certificates(3);
+        let tree = MerkleTree::new(certificates.iter().map(merkle_leaf).collect()).unwrap();
+
+        // the lot claims the manufacturer as owner but another key signed it
+        let mut signed = signed_batch(&manufacturer(), &certificates);
+        let other = wallet("5de4111afa1a4b94908f83103eb1f1706367c2e68ca870fc3fb9a804cdab365a");
+        let signature = other.sign_hash(signed.batch.digest().unwrap()).unwrap();
+        signed.signature = format!("0x{}", signature);
+
+        assert!(matches!(
+            verify_batched(&certificates[0], &tree.proof(0), &signed),
+            Err(CertificateError::SignerNotOwner { .. })
+        ));
+    }
+
+    #[test]
+    fn batch_digest_is_stable() {
+        let signed = signed_batch(&manufacturer(), &certificates(3));
+        let digest = signed.batch.digest().unwrap();
+
+        // same bytes as the generic EIP-712 encoder produces for the typed data
+        let typed = signed.batch.typed_data().unwrap();
+        assert_eq!(digest, H256::from(typed.encode_eip712().unwrap()));
+        assert_eq!(
+            format!("{:?}", signed.batch.root),
+            "0xd8577d70fadb55ac7630f076242aea9dce8cead66ad0c8030f975880e5a61607"
+        );
+        assert_eq!(
+            format!("{:?}", digest),
+            "0x99f067522cb42b15506d1d480aff3208946ee7375b66871cf9ce8ddc34ab2b5e"
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use crate::typed_struct::TypedStruct;
+use ethers_core::abi::Token;
+use ethers_core::types::transaction::eip712::{EIP712Domain, Eip712, Eip712Error, TypedData};
+use ethers_core::types::{Address, H256, U256};
+use ethers_core::utils::keccak256;
+use serde::{Deserialize, Serialize};
+
+// Passed to the Authenticity constructor as `certificate`, the contract stores its hash
+pub const CERTIFICATE_TYPE: &str = <Certificate as TypedStruct>::ENCODED_TYPE;
+
+// Certificate struct for EIP-712
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Certificate {
+    pub name: String,
+    pub unique_id: String,
+    pub serial: String,
+    pub date: U256,
+    pub owner: Address,
+    pub metadata_hash: [u8; 32],
+    pub metadata: Vec<String>,
+    // the domain the certificate is signed under, set with `with_domain` before hashing
+    #[serde(skip)]
+    pub domain: EIP712Domain,
+}
+
+// the signed fields, in order. The type string, the `types` JSON and the struct hash all come from here
+crate::typed_struct!(Certificate, "Certificate", {
+    name: String => ("name", "string"),
+    unique_id: String => ("uniqueId", "string"),
+    serial: String => ("serial", "string"),
+    date: U256 => ("date", "uint256"),
+    owner: Address => ("owner", "address"),
+    metadata_hash: [u8; 32] => ("metadataHash", "bytes32"),
+});
+
+impl Certificate {
+    pub fn new(
+        name: String,
+        unique_id: String,
+        serial: String,
+        date: u64,
+        owner: Address,
+        metadata: Vec<String>,
+    ) -> Self {
+        Self {
+            name,
+            unique_id,
+            serial,
+            date: U256::from(date),
+            owner,
+            metadata_hash: to_meta_hash(&metadata),
+            metadata,
+            domain: EIP712Domain::default(),
+        }
+    }
+
+    pub fn with_domain(mut self, domain: EIP712Domain) -> Self {
+        self.domain = domain;
+        self
+    }
+
+    /// The EIP-712 digest that gets signed and recovered
+    pub fn digest(&self) -> Result<H256, Eip712Error> {
+        self.encode_eip712().map(H256::from)
+    }
+
+    /// The full `eth_signTypedData_v4` payload, for wallets and remote signers
+    pub fn typed_data(&self) -> Result<TypedData, Eip712Error> {
+        typed_data(self, self.domain()?)
+    }
+}
+
+// Name of the domain entry in a typed data `types` map
+const EIP712_DOMAIN_TYPE: &str = "EIP712Domain";
+
+// The `eth_signTypedData_v4` payload for any typed struct signed under `domain`
+pub(crate) fn typed_data<T: TypedStruct>(
+    value: &T,
+    domain: EIP712Domain,
+) -> Result<TypedData, Eip712Error> {
+    let mut types = T::types_json();
+    types[EIP712_DOMAIN_TYPE] = serde_json::json!([
+        { "name": "name", "type": "string" },
+        { "name": "version", "type": "string" },
+        { "name": "chainId", "type": "uint256" },
+        { "name": "verifyingContract", "type": "address" },
+    ]);
+
+    serde_json::from_value(serde_json::json!({
+        "types": types,
+        "primaryType": T::PRIMARY_TYPE,
+        "domain": domain,
+        "message": value.field_values(),
+    }))
+    .map_err(|e| Eip712Error::Message(e.to_string()))
+}
+
+// an unset domain would still hash, but to a digest the contract never accepts
+pub(crate) fn checked_domain(domain: &EIP712Domain) -> Result<EIP712Domain, Eip712Error> {
+    if domain.verifying_contract.is_none() || domain.chain_id.is_none() {
+        return Err(Eip712Error::Message(
+            "certificate signing domain is not set".to_string(),
+        ));
+    }
+
+    Ok(domain.clone())
+}
+
+pub(crate) fn domain_separator(domain: &EIP712Domain) -> [u8; 32] {
+    let type_hash = keccak256(
+        "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)",
+    );
+
+    let name_hash = keccak256(domain.name.clone().unwrap_or_default().as_bytes());
+    let version_hash = keccak256(domain.version.clone().unwrap_or_default().as_bytes());
+    let chain_id = domain.chain_id.unwrap_or_default();
+    let verifying_contract = domain.verifying_contract.unwrap_or_default();
+
+    let encoded = ethers_core::abi::encode(&[
+        Token::FixedBytes(type_hash.to_vec()),
+        Token::FixedBytes(name_hash.to_vec()),
+        Token::FixedBytes(version_hash.to_vec()),
+        Token::Uint(chain_id),
+        Token::Address(verifying_contract),
+    ]);
+    keccak256(&encoded)
+}
+
+// keccak256("\x19\x01" || domainSeparator || structHash), the EIP-712 digest the
+// contract's `_hashTypedDataV4` computes. Not the "\x19Ethereum Signed Message" prefix
+pub(crate) fn typed_digest(domain_separator: [u8; 32], struct_hash: [u8; 32]) -> [u8; 32] {
+    let mut bytes = Vec::with_capacity(2 + 32 + 32);
+    bytes.extend_from_slice(b"\x19\x01");
+    bytes.extend_from_slice(&domain_separator);
+    bytes.extend_from_slice(&struct_hash);
+
+    keccak256(&bytes)
+}
+
+// The domain the Authenticity contract hashes certificates under
+pub fn certificate_domain(
+    name: &str,
+    version: &str,
+    chain_id: u64,
+    verifying_contract: Address,
+) -> EIP712Domain {
+    EIP712Domain {
+        name: Some(name.to_string()),
+        version: Some(version.to_string()),
+        chain_id: Some(U256::from(chain_id)),
+        verifying_contract: Some(verifying_contract),
+        salt: None,
+    }
+}
+
+// Same as keccak256(abi.encode(metadata)) in the contract
+pub fn to_meta_hash(metadata: &[String]) -> [u8; 32] {
+    let metadata_bytes = ethers_core::abi::encode(&[Token::Array(
+        metadata.iter().map(|s| Token::String(s.clone())).collect(),
+    )]);
+    keccak256(&metadata_bytes)
+}
+
+// EIP-712 implementation
+impl Eip712 for Certificate {
+    type Error = Eip712Error;
+
+    fn domain_separator(&self) -> Result<[u8; 32], Self::Error> {
+        Ok(domain_separator(&self.domain()?))
+    }
+
+    fn domain(&self) -> Result<EIP712Domain, Self::Error> {
+        checked_domain(&self.domain)
+    }
+
+    fn type_hash() -> Result<[u8; 32], Self::Error> {
+        Ok(Self::hash_type())
+    }
+
+    fn struct_hash(&self) -> Result<[u8; 32], Self::Error> {
+        Ok(self.hash_struct())
+    }
+
+    fn encode_eip712(&self) -> Result<[u8; 32], Self::Error> {
+        Ok(typed_digest(self.domain_separator()?, self.struct_hash()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn certificate() -> Certificate {
+        Certificate::new(
+            "Phone".to_string(),
+            "IMEI1".to_string(),
+            "SN1".to_string(),
+            1_700_000_000,
+            Address::repeat_byte(0x70),
+            vec!["red".to_string(), "128GB".to_string()],
+        )
+        .with_domain(certificate_domain(
+            "ERI",
+            "1",
+            31337,
+            Address::repeat_byte(0x11),
+        ))
+    }
+
+    #[test]
+    fn certificate_type_matches_the_contract() {
+        // the string the contract tests pass to the Authenticity constructor, its hash is
+        // CERTIFICATE_TYPE_HASH and verifySignature encodes the fields in this order
+        assert_eq!(
+            CERTIFICATE_TYPE,
+            "Certificate(string name,string uniqueId,string serial,uint256 date,address owner,bytes32 metadataHash)"
+        );
+        assert!(include_str!("../../test/Authenticity.t.sol").contains(CERTIFICATE_TYPE));
+        assert_eq!(
+            Certificate::type_hash().unwrap(),
+            keccak256(CERTIFICATE_TYPE)
+        );
+    }
+
+    #[test]
+    fn digest_matches_ethers_typed_data() {
+        let certificate = certificate();
+
+        // through JSON, as a wallet receives it
+        let json = serde_json::to_string(&certificate.typed_data().unwrap()).unwrap();
+        let typed: TypedData = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(typed.primary_type, "Certificate");
+        assert_eq!(
+            typed.domain_separator().unwrap(),
+            certificate.domain_separator().unwrap()
+        );
+        assert_eq!(
+            typed.struct_hash().unwrap(),
+            certificate.struct_hash().unwrap()
+        );
+        assert_eq!(
+            typed.encode_eip712().unwrap(),
+            certificate.encode_eip712().unwrap()
+        );
+    }
+
+    #[test]
+    fn digest_needs_a_domain() {
+        let mut certificate = certificate();
+        certificate.domain = EIP712Domain::default();
+
+        assert!(certificate.digest().is_err());
+    }
+
+    #[test]
+    fn metadata_hash_is_abi_encoded_array() {
+        // keccak256(abi.encode(new string[](0))): offset 0x20, then length 0
+        let mut empty = [0u8; 64];
+        empty[31] = 0x20;
+
+        assert_eq!(to_meta_hash(&[]), keccak256(empty));
+        assert_eq!(
+            certificate().metadata_hash,
+            to_meta_hash(&certificate().metadata)
+        );
+    }
+}
@@ -0,0 +1,29 @@
+use ethers_core::types::transaction::eip712::Eip712Error;
+use ethers_core::types::{Address, H256, SignatureError};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CertificateError {
+    #[error("invalid address {0:?}")]
+    InvalidAddress(String),
+    #[error("invalid signature format: {0}")]
+    InvalidSignatureFormat(String),
+    #[error("EIP-712 encoding failed: {0}")]
+    Eip712(#[from] Eip712Error),
+    #[error("signer recovery failed: {0}")]
+    Recovery(#[from] SignatureError),
+    #[error("signing failed: {0}")]
+    Signing(String),
+    #[error("certificate is signed by {signer:?}, not by its owner {owner:?}")]
+    SignerNotOwner { signer: Address, owner: Address },
+    #[error("{0:?} is not a registered manufacturer")]
+    ManufacturerNotRegistered(Address),
+    #[error("invalid QR payload: {0}")]
+    InvalidQrPayload(String),
+    #[error("a batch needs at least one certificate")]
+    EmptyBatch,
+    #[error("certificate is not part of the batch with root {root:?}")]
+    NotInBatch { root: H256 },
+    #[error("registry snapshot is signed by {signer:?}, expected {trusted:?}")]
+    UntrustedSnapshot { signer: Address, trusted: Address },
+}
@@ -0,0 +1,33 @@
+//! Certificate construction, EIP-712 hashing, signing and signer recovery for ERI.
+//!
+//! Nothing in here talks to the chain or reads the environment, so it can be used
+//! by any service that issues or checks certificates.
+
+pub mod batch;
+pub mod certificate;
+pub mod error;
+pub mod qr_payload;
+pub mod registry;
+pub mod signing;
+pub mod typed_struct;
+
+pub use batch::{
+    CertificateBatch, MerkleTree, SignedCertificateBatch, merkle_leaf, process_proof, verify_batched,
+};
+pub use certificate::{CERTIFICATE_TYPE, Certificate, certificate_domain, to_meta_hash};
+pub use error::CertificateError;
+pub use qr_payload::{
+    QR_PAYLOAD_VERSION, decode_qr_payload, encode_qr_payload, encode_qr_url_payload,
+};
+pub use registry::{RegistryEntry, RegistrySnapshot, SignedRegistrySnapshot, verify_offline};
+pub use signing::{
+    parse_signature, recover_signer, sign_certificate, signature_from_bytes, verify_owner_signature,
+};
+pub use typed_struct::TypedStruct;
+
+// used by `typed_struct!` so callers don't need these crates themselves
+#[doc(hidden)]
+pub mod reexports {
+    pub use ethers_core;
+    pub use serde_json;
+}
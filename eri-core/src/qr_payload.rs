@@ -0,0 +1,389 @@
+use crate::certificate::Certificate;
+use crate::error::CertificateError;
+use crate::signing::signature_from_bytes;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL;
+use ciborium::value::Value;
+use ethers_core::types::{Address, Signature};
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use std::io::Read;
+
+// Compact QR payloads are `ERI<version>:` followed by the base45 text of a zlib
+// compressed CBOR array, the layout EU digital COVID certificates use. Every character
+// is in the QR alphanumeric set, so the code is rendered in alphanumeric mode
+// (5.5 bits a character instead of 8).
+// URLs can't carry base45 (space, `%` and `/` are digits), so links hold the same bytes
+// as `ERI<version>.` followed by unpadded base64url instead
+pub const QR_PAYLOAD_PREFIX: &str = "ERI";
+pub const QR_PAYLOAD_VERSION: u8 = 1;
+
+// a QR code holds under 3 KB, anything inflating past this is not one of ours
+const MAX_CBOR_LEN: u64 = 64 * 1024;
+
+fn invalid(message: impl Into<String>) -> CertificateError {
+    CertificateError::InvalidQrPayload(message.into())
+}
+
+pub fn encode_qr_payload(
+    certificate: &Certificate,
+    signature: &Signature,
+) -> Result<String, CertificateError> {
+    Ok(format!(
+        "{}{}:{}",
+        QR_PAYLOAD_PREFIX,
+        QR_PAYLOAD_VERSION,
+        base45_encode(&compress(certificate, signature)?)
+    ))
+}
+
+// The compact payload in a form that fits a URL path segment or query value
+pub fn encode_qr_url_payload(
+    certificate: &Certificate,
+    signature: &Signature,
+) -> Result<String, CertificateError> {
+    Ok(format!(
+        "{}{}.{}",
+        QR_PAYLOAD_PREFIX,
+        QR_PAYLOAD_VERSION,
+        BASE64_URL.encode(compress(certificate, signature)?)
+    ))
+}
+
+// Version 1 is [name, unique_id, serial, date, owner, metadata, signature], with the date
+// as a CBOR unsigned integer (1 to 9 bytes) and the owner and signature as raw bytes
+fn compress(certificate: &Certificate, signature: &Signature) -> Result<Vec<u8>, CertificateError> {
+    let date = u64::try_from(certificate.date)
+        .map_err(|_| invalid("certificate date does not fit in 64 bits"))?;
+
+    let value = Value::Array(vec![
+        Value::Text(certificate.name.clone()),
+        Value::Text(certificate.unique_id.clone()),
+        Value::Text(certificate.serial.clone()),
+        Value::Integer(date.into()),
+        Value::Bytes(certificate.owner.as_bytes().to_vec()),
+        Value::Array(
+            certificate
+                .metadata
+                .iter()
+                .map(|entry| Value::Text(entry.clone()))
+                .collect(),
+        ),
+        Value::Bytes(signature.to_vec()),
+    ]);
+
+    let mut zlib = ZlibEncoder::new(Vec::new(), Compression::best());
+    ciborium::into_writer(&value, &mut zlib).map_err(|e| invalid(e.to_string()))?;
+    zlib.finish().map_err(|e| invalid(e.to_string()))
+}
+
+// The certificate (without a signing domain) and signature a compact payload carries,
+// in either its QR (`:`) or URL (`.`) form
+pub fn decode_qr_payload(payload: &str) -> Result<(Certificate, Signature), CertificateError> {
+    // not trim(), a space is a base45 digit
+    let (version, separator, body) = payload
+        .trim_end_matches(['\r', '\n'])
+        .strip_prefix(QR_PAYLOAD_PREFIX)
+        .and_then(|rest| {
+            let split = rest.find([':', '.'])?;
+            Some((&rest[..split], &rest[split..=split], &rest[split + 1..]))
+        })
+        .ok_or_else(|| {
+            invalid(format!(
+                "missing the {}<version>: prefix",
+                QR_PAYLOAD_PREFIX
+            ))
+        })?;
+
+    match version.parse::<u8>() {
+        Ok(QR_PAYLOAD_VERSION) => {}
+        _ => {
+            return Err(invalid(format!(
+                "unsupported payload version {:?}",
+                version
+            )));
+        }
+    }
+
+    let compressed = if separator == ":" {
+        base45_decode(body)?
+    } else {
+        BASE64_URL
+            .decode(body)
+            .map_err(|e| invalid(format!("invalid base64url: {}", e)))?
+    };
+    let mut cbor = Vec::new();
+    ZlibDecoder::new(compressed.as_slice())
+        .take(MAX_CBOR_LEN + 1)
+        .read_to_end(&mut cbor)
+        .map_err(|e| invalid(e.to_string()))?;
+    if cbor.len() as u64 > MAX_CBOR_LEN {
+        return Err(invalid("payload inflates past 64 KiB"));
+    }
+    let value: Value =
+        ciborium::from_reader(cbor.as_slice()).map_err(|e| invalid(e.to_string()))?;
+
+    let Value::Array(fields) = value else {
+        return Err(invalid("payload is not a CBOR array"));
+    };
+    let [name, unique_id, serial, date, owner, metadata, signature] =
+        <[Value; 7]>::try_from(fields)
+            .map_err(|fields| invalid(format!("expected 7 fields, got {}", fields.len())))?;
+
+    let owner = bytes(owner, "owner")?;
+    if owner.len() != 20 {
+        return Err(invalid("owner is not 20 bytes"));
+    }
+    let Value::Array(metadata) = metadata else {
+        return Err(invalid("metadata is not an array"));
+    };
+    let metadata = metadata
+        .into_iter()
+        .map(|entry| text(entry, "metadata"))
+        .collect::<Result<Vec<_>, _>>()?;
+    let date = match date {
+        Value::Integer(date) => u64::try_from(date).map_err(|_| invalid("date is out of range"))?,
+        _ => return Err(invalid("date is not an unsigned integer")),
+    };
+    let signature = signature_from_bytes(&bytes(signature, "signature")?)?;
+
+    let certificate = Certificate::new(
+        text(name, "name")?,
+        text(unique_id, "unique_id")?,
+        text(serial, "serial")?,
+        date,
+        Address::from_slice(&owner),
+        metadata,
+    );
+
+    Ok((certificate, signature))
+}
+
+fn text(value: Value, field: &str) -> Result<String, CertificateError> {
+    match value {
+        Value::Text(text) => Ok(text),
+        _ => Err(invalid(format!("{} is not a text string", field))),
+    }
+}
+
+fn bytes(value: Value, field: &str) -> Result<Vec<u8>, CertificateError> {
+    match value {
+        Value::Bytes(bytes) => Ok(bytes),
+        _ => Err(invalid(format!("{} is not a byte string", field))),
+    }
+}
+
+// RFC 9285 base45
+const BASE45_ALPHABET: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+fn base45_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(2) * 3);
+    for chunk in data.chunks(2) {
+        let (mut n, digits) = match chunk {
+            [a, b] => (usize::from(*a) * 256 + usize::from(*b), 3),
+            [a] => (usize::from(*a), 2),
+            _ => unreachable!(),
+        };
+        for _ in 0..digits {
+            out.push(char::from(BASE45_ALPHABET[n % 45]));
+            n /= 45;
+        }
+    }
+    out
+}
+
+fn base45_decode(text: &str) -> Result<Vec<u8>, CertificateError> {
+    let digits =
+        text.bytes()
+            .map(|c| {
+                BASE45_ALPHABET.iter().position(|&a| a == c).ok_or_else(|| {
+                    invalid(format!("{:?} is not a base45 character", char::from(c)))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+    let mut out = Vec::with_capacity(digits.len() / 3 * 2 + 1);
+    for group in digits.chunks(3) {
+        match group {
+            [c, d, e] => {
+                let n = c + d * 45 + e * 45 * 45;
+                if n > 0xFFFF {
+                    return Err(invalid("base45 group out of range"));
+                }
+                out.push((n >> 8) as u8);
+                out.push(n as u8);
+            }
+            [c, d] => {
+                let n = c + d * 45;
+                if n > 0xFF {
+                    return Err(invalid("base45 group out of range"));
+                }
+                out.push(n as u8);
+            }
+            _ => return Err(invalid("truncated base45 text")),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers_core::types::U256;
+    use std::io::Write;
+
+    fn certificate() -> Certificate {
+        Certificate::new(
+            "Phone".to_string(),
+            "IMEI1".to_string(),
+            "SN1".to_string(),
+            1_700_000_000,
+            Address::repeat_byte(0x70),
+            vec!["red".to_string(), "128GB".to_string()],
+        )
+    }
+
+    fn signature() -> Signature {
+        Signature {
+            r: U256::from(1),
+            s: U256::from(2),
+            v: 28,
+        }
+    }
+
+    fn assert_round_trip(payload: &str) {
+        let (decoded, decoded_signature) = decode_qr_payload(payload).unwrap();
+        let expected = certificate();
+
+        assert_eq!(decoded.name, expected.name);
+        assert_eq!(decoded.unique_id, expected.unique_id);
+        assert_eq!(decoded.serial, expected.serial);
+        assert_eq!(decoded.date, expected.date);
+        assert_eq!(decoded.owner, expected.owner);
+        assert_eq!(decoded.metadata, expected.metadata);
+        assert_eq!(decoded.metadata_hash, expected.metadata_hash);
+        assert_eq!(decoded_signature, signature());
+    }
+
+    // a URL form payload around any zlib compressed bytes
+    fn payload_from_cbor(cbor: &[u8]) -> String {
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::best());
+        zlib.write_all(cbor).unwrap();
+        format!("ERI1.{}", BASE64_URL.encode(zlib.finish().unwrap()))
+    }
+
+    fn payload_from_fields(fields: Vec<Value>) -> String {
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&Value::Array(fields), &mut cbor).unwrap();
+        payload_from_cbor(&cbor)
+    }
+
+    fn fields() -> Vec<Value> {
+        vec![
+            Value::Text("Phone".to_string()),
+            Value::Text("IMEI1".to_string()),
+            Value::Text("SN1".to_string()),
+            Value::Integer(1_700_000_000u64.into()),
+            Value::Bytes(vec![0x70; 20]),
+            Value::Array(Vec::new()),
+            Value::Bytes(signature().to_vec()),
+        ]
+    }
+
+    #[test]
+    fn base45_matches_rfc_9285_vectors() {
+        assert_eq!(base45_encode(b"AB"), "BB8");
+        assert_eq!(base45_encode(b"Hello!!"), "%69 VD92EX0");
+        assert_eq!(base45_encode(b"base-45"), "UJCLQE7W581");
+
+        assert_eq!(base45_decode("BB8").unwrap(), b"AB");
+        assert_eq!(base45_decode("%69 VD92EX0").unwrap(), b"Hello!!");
+        assert_eq!(base45_decode("QED8WEX0").unwrap(), b"ietf!");
+    }
+
+    #[test]
+    fn base45_rejects_bad_groups() {
+        // 16 + 16 * 45 + 32 * 45^2 = 65536
+        assert!(base45_decode("GGW").is_err());
+        // a two digit group above 255
+        assert!(base45_decode("::").is_err());
+        assert!(base45_decode("BB8B").is_err());
+        assert!(base45_decode("bb8").is_err());
+    }
+
+    #[test]
+    fn qr_form_round_trips() {
+        let payload = encode_qr_payload(&certificate(), &signature()).unwrap();
+
+        assert!(payload.starts_with("ERI1:"));
+        assert!(payload[5..].bytes().all(|c| BASE45_ALPHABET.contains(&c)));
+        assert_round_trip(&payload);
+        // scanners often append a line break
+        assert_round_trip(&format!("{}\r\n", payload));
+    }
+
+    #[test]
+    fn url_form_round_trips() {
+        let payload = encode_qr_url_payload(&certificate(), &signature()).unwrap();
+
+        assert!(payload.starts_with("ERI1."));
+        assert!(
+            payload[5..]
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_')
+        );
+        assert_round_trip(&payload);
+    }
+
+    #[test]
+    fn truncated_payload_is_rejected() {
+        let payload = encode_qr_payload(&certificate(), &signature()).unwrap();
+
+        assert!(decode_qr_payload(&payload[..payload.len() - 1]).is_err());
+        assert!(decode_qr_payload(&payload[..payload.len() / 2]).is_err());
+        assert!(decode_qr_payload("ERI1:").is_err());
+    }
+
+    #[test]
+    fn unknown_version_is_rejected() {
+        let payload = encode_qr_payload(&certificate(), &signature()).unwrap();
+
+        assert!(decode_qr_payload(&payload.replacen("ERI1", "ERI2", 1)).is_err());
+        assert!(decode_qr_payload(&payload.replacen("ERI1", "ERIx", 1)).is_err());
+        assert!(decode_qr_payload(&payload.replacen("ERI1:", "", 1)).is_err());
+    }
+
+    #[test]
+    fn wrong_field_count_is_rejected() {
+        assert!(decode_qr_payload(&payload_from_fields(fields())).is_ok());
+
+        let mut six = fields();
+        six.pop();
+        assert!(decode_qr_payload(&payload_from_fields(six)).is_err());
+
+        let mut eight = fields();
+        eight.push(Value::Null);
+        assert!(decode_qr_payload(&payload_from_fields(eight)).is_err());
+    }
+
+    #[test]
+    fn short_owner_is_rejected() {
+        let mut fields = fields();
+        fields[4] = Value::Bytes(vec![0x70; 19]);
+
+        assert!(decode_qr_payload(&payload_from_fields(fields)).is_err());
+    }
+
+    #[test]
+    fn zlib_bomb_is_rejected() {
+        // compresses to a few hundred bytes, inflates one byte past the limit
+        let payload = payload_from_cbor(&vec![0; MAX_CBOR_LEN as usize + 1]);
+
+        assert!(payload.len() < 1024);
+        assert!(matches!(
+            decode_qr_payload(&payload),
+            Err(CertificateError::InvalidQrPayload(message)) if message.contains("64 KiB")
+        ));
+    }
+}
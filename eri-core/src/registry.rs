@@ -0,0 +1,276 @@
+use crate::certificate::{Certificate, certificate_domain};
+use crate::error::CertificateError;
+use crate::signing::{parse_signature, verify_owner_signature};
+use ethers_core::abi::Token;
+use ethers_core::types::transaction::eip712::EIP712Domain;
+use ethers_core::types::{Address, H256, Signature};
+use ethers_core::utils::keccak256;
+use ethers_signers::Signer;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RegistryEntry {
+    pub address: Address,
+    pub name: String,
+}
+
+// The Authenticity manufacturer registry as of one block, enough to verify
+// certificates with no RPC access
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegistrySnapshot {
+    pub chain_id: u64,
+    pub contract: Address,
+    /// EIP-712 domain name and version the contract was deployed with
+    pub domain_name: String,
+    pub domain_version: String,
+    pub block_number: u64,
+    pub block_hash: H256,
+    /// Block timestamp, staleness is measured from here
+    pub timestamp: u64,
+    pub manufacturers: Vec<RegistryEntry>,
+}
+
+impl RegistrySnapshot {
+    pub fn domain(&self) -> EIP712Domain {
+        certificate_domain(
+            &self.domain_name,
+            &self.domain_version,
+            self.chain_id,
+            self.contract,
+        )
+    }
+
+    /// Hash over every field, signed as an EIP-191 message
+    pub fn digest(&self) -> H256 {
+        let manufacturers = self
+            .manufacturers
+            .iter()
+            .map(|entry| {
+                Token::Tuple(vec![
+                    Token::Address(entry.address),
+                    Token::String(entry.name.clone()),
+                ])
+            })
+            .collect();
+
+        let encoded = ethers_core::abi::encode(&[
+            Token::String("ERI registry snapshot".to_string()),
+            Token::Uint(self.chain_id.into()),
+            Token::Address(self.contract),
+            Token::String(self.domain_name.clone()),
+            Token::String(self.domain_version.clone()),
+            Token::Uint(self.block_number.into()),
+            Token::FixedBytes(self.block_hash.as_bytes().to_vec()),
+            Token::Uint(self.timestamp.into()),
+            Token::Array(manufacturers),
+        ]);
+        H256::from(keccak256(encoded))
+    }
+
+    pub fn lookup(&self, address: Address) -> Option<&RegistryEntry> {
+        self.manufacturers
+            .iter()
+            .find(|entry| entry.address == address)
+    }
+
+    pub fn age_secs(&self, now: u64) -> u64 {
+        now.saturating_sub(self.timestamp)
+    }
+
+    pub async fn sign<S: Signer>(
+        self,
+        signer: &S,
+    ) -> Result<SignedRegistrySnapshot, CertificateError> {
+        let signature = signer
+            .sign_message(self.digest().as_bytes())
+            .await
+            .map_err(|e| CertificateError::Signing(e.to_string()))?;
+
+        Ok(SignedRegistrySnapshot {
+            signer: signer.address(),
+            signature: format!("0x{}", signature),
+            snapshot: self,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedRegistrySnapshot {
+    #[serde(flatten)]
+    pub snapshot: RegistrySnapshot,
+    pub signer: Address,
+    pub signature: String,
+}
+
+impl SignedRegistrySnapshot {
+    /// Checks the snapshot was signed by `trusted_signer` and not modified since
+    pub fn verify(&self, trusted_signer: Address) -> Result<&RegistrySnapshot, CertificateError> {
+        let signature = parse_signature(&self.signature)?;
+        let signer = signature.recover(ethers_core::utils::hash_message(
+            self.snapshot.digest().as_bytes(),
+        ))?;
+
+        if signer != trusted_signer || signer != self.signer {
+            return Err(CertificateError::UntrustedSnapshot {
+                signer,
+                trusted: trusted_signer,
+            });
+        }
+
+        Ok(&self.snapshot)
+    }
+}
+
+// Verifies a certificate against the snapshot instead of calling getManufacturer.
+// Returns the signer and the manufacturer name it is registered under
+pub fn verify_offline(
+    snapshot: &RegistrySnapshot,
+    certificate: Certificate,
+    signature: &Signature,
+) -> Result<(Address, String), CertificateError> {
+    let certificate = certificate.with_domain(snapshot.domain());
+    let signer = verify_owner_signature(&certificate, signature)?;
+
+    let entry = snapshot
+        .lookup(signer)
+        .ok_or(CertificateError::ManufacturerNotRegistered(signer))?;
+
+    Ok((signer, entry.name.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::sign_certificate;
+    use ethers_signers::LocalWallet;
+
+    const SERVER_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const MANUFACTURER_KEY: &str =
+        "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+    const OTHER_KEY: &str = "5de4111afa1a4b94908f83103eb1f1706367c2e68ca870fc3fb9a804cdab365a";
+
+    fn wallet(key: &str) -> LocalWallet {
+        key.parse().unwrap()
+    }
+
+    fn snapshot() -> RegistrySnapshot {
+        RegistrySnapshot {
+            chain_id: 31337,
+            contract: Address::repeat_byte(0x11),
+            domain_name: "ERI".to_string(),
+            domain_version: "1".to_string(),
+            block_number: 100,
+            block_hash: H256::repeat_byte(0xbb),
+            timestamp: 1_700_000_000,
+            manufacturers: vec![RegistryEntry {
+                address: wallet(MANUFACTURER_KEY).address(),
+                name: "Acme".to_string(),
+            }],
+        }
+    }
+
+    fn certificate(owner: Address) -> Certificate {
+        Certificate::new(
+            "Phone".to_string(),
+            "IMEI1".to_string(),
+            "SN1".to_string(),
+            1_700_000_000,
+            owner,
+            vec!["red".to_string()],
+        )
+    }
+
+    async fn signed_certificate(key: &str, domain: EIP712Domain) -> (Certificate, Signature) {
+        let signer = wallet(key);
+        let certificate = certificate(signer.address()).with_domain(domain);
+        let signature = sign_certificate(&signer, &certificate).await.unwrap();
+        (certificate, signature)
+    }
+
+    #[tokio::test]
+    async fn signed_snapshot_verifies() {
+        let signed = snapshot().sign(&wallet(SERVER_KEY)).await.unwrap();
+
+        let verified = signed.verify(wallet(SERVER_KEY).address()).unwrap();
+
+        assert_eq!(verified.block_number, 100);
+        assert_eq!(verified.manufacturers, snapshot().manufacturers);
+        // and survives the JSON file it is shipped in
+        let json = serde_json::to_string(&signed).unwrap();
+        let parsed: SignedRegistrySnapshot = serde_json::from_str(&json).unwrap();
+        assert!(parsed.verify(wallet(SERVER_KEY).address()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn tampered_snapshot_is_rejected() {
+        let signed = snapshot().sign(&wallet(SERVER_KEY)).await.unwrap();
+        let trusted = wallet(SERVER_KEY).address();
+
+        let mut renamed = signed.clone();
+        renamed.snapshot.manufacturers[0].name = "Acme Outlet".to_string();
+        let mut rekeyed = signed.clone();
+        rekeyed.snapshot.manufacturers[0].address = wallet(OTHER_KEY).address();
+        let mut added = signed.clone();
+        added.snapshot.manufacturers.push(RegistryEntry {
+            address: wallet(OTHER_KEY).address(),
+            name: "Counterfeit".to_string(),
+        });
+        let mut rewound = signed.clone();
+        rewound.snapshot.timestamp += 86_400;
+
+        for tampered in [renamed, rekeyed, added, rewound] {
+            assert!(matches!(
+                tampered.verify(trusted),
+                Err(CertificateError::UntrustedSnapshot { .. })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn snapshot_from_another_signer_is_rejected() {
+        let signed = snapshot().sign(&wallet(OTHER_KEY)).await.unwrap();
+
+        assert!(matches!(
+            signed.verify(wallet(SERVER_KEY).address()),
+            Err(CertificateError::UntrustedSnapshot { .. })
+        ));
+
+        // claiming the trusted signer doesn't help, the signature is recovered
+        let mut claimed = signed.clone();
+        claimed.signer = wallet(SERVER_KEY).address();
+        assert!(claimed.verify(wallet(SERVER_KEY).address()).is_err());
+    }
+
+    #[tokio::test]
+    async fn certificate_verifies_against_the_snapshot() {
+        let snapshot = snapshot();
+        let (certificate, signature) =
+            signed_certificate(MANUFACTURER_KEY, snapshot.domain()).await;
+
+        let (signer, name) = verify_offline(&snapshot, certificate, &signature).unwrap();
+
+        assert_eq!(signer, wallet(MANUFACTURER_KEY).address());
+        assert_eq!(name, "Acme");
+    }
+
+    #[tokio::test]
+    async fn unregistered_signer_is_rejected() {
+        let snapshot = snapshot();
+        let (certificate, signature) = signed_certificate(OTHER_KEY, snapshot.domain()).await;
+
+        assert!(matches!(
+            verify_offline(&snapshot, certificate, &signature),
+            Err(CertificateError::ManufacturerNotRegistered(signer))
+                if signer == wallet(OTHER_KEY).address()
+        ));
+    }
+
+    #[tokio::test]
+    async fn certificate_from_another_deployment_is_rejected() {
+        let snapshot = snapshot();
+        let other = certificate_domain("ERI", "1", 1, Address::repeat_byte(0x11));
+        let (certificate, signature) = signed_certificate(MANUFACTURER_KEY, other).await;
+
+        assert!(verify_offline(&snapshot, certificate, &signature).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use crate::certificate::Certificate;
+use crate::error::CertificateError;
+use ethers_core::types::{Address, Signature, U256};
+use ethers_signers::Signer;
+
+// secp256k1n / 2. The contract's ECDSA.recover rejects any s above it, since (r, n - s)
+// is a second valid signature for the same message
+const SECP256K1_HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+// Signs the certificate's EIP-712 digest, the domain must be set with `with_domain`
+pub async fn sign_certificate<S: Signer>(
+    signer: &S,
+    certificate: &Certificate,
+) -> Result<Signature, CertificateError> {
+    signer
+        .sign_typed_data(certificate)
+        .await
+        .map_err(|e| CertificateError::Signing(e.to_string()))
+}
+
+// Parses a 0x-prefixed signature, see `signature_from_bytes` for the accepted forms
+pub fn parse_signature(signature: &str) -> Result<Signature, CertificateError> {
+    let bytes = hex::decode(signature.trim_start_matches("0x"))
+        .map_err(|e| CertificateError::InvalidSignatureFormat(e.to_string()))?;
+
+    signature_from_bytes(&bytes)
+}
+
+// Accepts a 65 byte r || s || v signature or a 64 byte EIP-2098 compact r || vs one, with
+// v as 0/1 or 27/28. The result always carries v = 27/28, and high-s signatures are
+// rejected so the verdict matches the contract's `verifySignature`.
+// Anything sent on chain must be the normalized 65 byte form (`signature.to_vec()`), OZ 5.x
+// ECDSA.recover reverts on 64 byte compact signatures
+pub fn signature_from_bytes(bytes: &[u8]) -> Result<Signature, CertificateError> {
+    let (r, s, v) = match bytes.len() {
+        65 => (
+            U256::from_big_endian(&bytes[..32]),
+            U256::from_big_endian(&bytes[32..64]),
+            u64::from(bytes[64]),
+        ),
+        // EIP-2098 packs the y parity into the top bit of s
+        64 => {
+            let mut vs = [0u8; 32];
+            vs.copy_from_slice(&bytes[32..]);
+            let parity = vs[0] >> 7;
+            vs[0] &= 0x7f;
+            (
+                U256::from_big_endian(&bytes[..32]),
+                U256::from_big_endian(&vs),
+                27 + u64::from(parity),
+            )
+        }
+        len => {
+            return Err(CertificateError::InvalidSignatureFormat(format!(
+                "expected 64 or 65 bytes, got {}",
+                len
+            )));
+        }
+    };
+
+    let v = match v {
+        0 | 1 => v + 27,
+        27 | 28 => v,
+        _ => {
+            return Err(CertificateError::InvalidSignatureFormat(format!(
+                "invalid recovery id v = {}",
+                v
+            )));
+        }
+    };
+    if s > U256::from_big_endian(&SECP256K1_HALF_ORDER) {
+        return Err(CertificateError::InvalidSignatureFormat(
+            "s is in the upper half of the curve order".to_string(),
+        ));
+    }
+
+    Ok(Signature { r, s, v })
+}
+
+// The address that produced `signature` over the certificate
+pub fn recover_signer(
+    certificate: &Certificate,
+    signature: &Signature,
+) -> Result<Address, CertificateError> {
+    let digest = certificate.digest()?;
+
+    //this caused big issue until I removed it
+    // let digest = hash_message(digest); // Prefix with \x19Ethereum Signed Message
+    Ok(signature.recover(digest)?)
+}
+
+// Recovers the signer and checks it is the certificate owner, as the contract does
+pub fn verify_owner_signature(
+    certificate: &Certificate,
+    signature: &Signature,
+) -> Result<Address, CertificateError> {
+    let signer = recover_signer(certificate, signature)?;
+
+    // very important: double check to make sure the certificate owner is the signer of the signature
+    if signer != certificate.owner {
+        return Err(CertificateError::SignerNotOwner {
+            signer,
+            owner: certificate.owner,
+        });
+    }
+
+    Ok(signer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers_core::types::H256;
+    use ethers_signers::LocalWallet;
+
+    fn wallet() -> LocalWallet {
+        "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d"
+            .parse()
+            .unwrap()
+    }
+
+    fn signed() -> Signature {
+        wallet().sign_hash(H256::repeat_byte(0x42)).unwrap()
+    }
+
+    fn rsv(s: U256, v: u8) -> Vec<u8> {
+        let mut bytes = [0u8; 65];
+        bytes[31] = 1;
+        s.to_big_endian(&mut bytes[32..64]);
+        bytes[64] = v;
+        bytes.to_vec()
+    }
+
+    #[test]
+    fn compact_signature_carries_parity_in_top_bit() {
+        for parity in [0, 1] {
+            let mut signature = signed();
+            // both recovery ids over the same (r, s), whichever the key produced
+            signature.v = 27 + parity;
+            let mut bytes = signature.to_vec();
+            bytes.truncate(64);
+            bytes[32] |= (parity as u8) << 7;
+
+            assert_eq!(signature_from_bytes(&bytes).unwrap(), signature);
+        }
+
+        // and the key's own compact form still recovers the key
+        let signature = signed();
+        let mut bytes = signature.to_vec();
+        bytes.truncate(64);
+        bytes[32] |= ((signature.v - 27) as u8) << 7;
+        let parsed = signature_from_bytes(&bytes).unwrap();
+        assert_eq!(
+            parsed.recover(H256::repeat_byte(0x42)).unwrap(),
+            wallet().address()
+        );
+    }
+
+    #[test]
+    fn v_zero_and_one_normalize_to_27_and_28() {
+        let signature = signed();
+        let mut bytes = signature.to_vec();
+        bytes[64] = (signature.v - 27) as u8;
+
+        assert_eq!(signature_from_bytes(&bytes).unwrap(), signature);
+        assert!(signature_from_bytes(&rsv(U256::one(), 2)).is_err());
+    }
+
+    #[test]
+    fn s_above_half_order_is_rejected() {
+        let half = U256::from_big_endian(&SECP256K1_HALF_ORDER);
+
+        assert_eq!(signature_from_bytes(&rsv(half, 27)).unwrap().s, half);
+        assert!(signature_from_bytes(&rsv(half + 1, 27)).is_err());
+    }
+
+    #[test]
+    fn other_lengths_are_rejected() {
+        let bytes = signed().to_vec();
+
+        assert!(signature_from_bytes(&bytes[..63]).is_err());
+        assert!(signature_from_bytes(&[bytes.as_slice(), &[0]].concat()).is_err());
+        assert!(parse_signature(&format!("0x{}", hex::encode(&bytes[..63]))).is_err());
+    }
+}
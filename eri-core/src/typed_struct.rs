@@ -0,0 +1,220 @@
+use ethers_core::abi::Token;
+use ethers_core::types::{Address, H256, U256};
+use ethers_core::utils::{keccak256, to_checksum};
+use serde_json::{Map, Value};
+
+// One member of an EIP-712 struct type, as it appears in the `types` JSON
+#[derive(Clone, Copy, Debug)]
+pub struct TypedField {
+    pub name: &'static str,
+    pub kind: &'static str,
+}
+
+// A struct that is signed as EIP-712 typed data. Implement it with `typed_struct!`
+// so the type string, the `types` JSON and the struct hash share one field list
+pub trait TypedStruct {
+    const PRIMARY_TYPE: &'static str;
+    /// e.g. `Certificate(string name,...)`, built at compile time
+    const ENCODED_TYPE: &'static str;
+    const FIELDS: &'static [TypedField];
+
+    /// Field values encoded for hashStruct (dynamic types already hashed)
+    fn encode_fields(&self) -> Vec<Token>;
+
+    /// Field values as `eth_signTypedData_v4` expects them in `message`
+    fn field_values(&self) -> Map<String, Value>;
+
+    // named after the EIP-712 spec functions so they don't clash with the ethers Eip712 trait
+    fn hash_type() -> [u8; 32] {
+        keccak256(Self::ENCODED_TYPE)
+    }
+
+    fn hash_struct(&self) -> [u8; 32] {
+        let mut tokens = vec![Token::FixedBytes(Self::hash_type().to_vec())];
+        tokens.extend(self.encode_fields());
+        keccak256(ethers_core::abi::encode(&tokens))
+    }
+
+    fn types_json() -> Value {
+        let fields = Self::FIELDS
+            .iter()
+            .map(|field| serde_json::json!({ "name": field.name, "type": field.kind }))
+            .collect::<Vec<_>>();
+
+        let mut types = Map::new();
+        types.insert(Self::PRIMARY_TYPE.to_string(), Value::Array(fields));
+        Value::Object(types)
+    }
+}
+
+// Rust types that can be a member of a TypedStruct
+pub trait Eip712Field {
+    const SOL_TYPE: &'static str;
+
+    fn encode_field(&self) -> Token;
+
+    fn to_json(&self) -> Value;
+}
+
+impl Eip712Field for String {
+    const SOL_TYPE: &'static str = "string";
+
+    fn encode_field(&self) -> Token {
+        Token::FixedBytes(keccak256(self.as_bytes()).to_vec())
+    }
+
+    fn to_json(&self) -> Value {
+        Value::String(self.clone())
+    }
+}
+
+impl Eip712Field for U256 {
+    const SOL_TYPE: &'static str = "uint256";
+
+    fn encode_field(&self) -> Token {
+        Token::Uint(*self)
+    }
+
+    // as a decimal string, JSON numbers can't hold a uint256
+    fn to_json(&self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl Eip712Field for Address {
+    const SOL_TYPE: &'static str = "address";
+
+    fn encode_field(&self) -> Token {
+        Token::Address(*self)
+    }
+
+    fn to_json(&self) -> Value {
+        Value::String(to_checksum(self, None))
+    }
+}
+
+impl Eip712Field for [u8; 32] {
+    const SOL_TYPE: &'static str = "bytes32";
+
+    fn encode_field(&self) -> Token {
+        Token::FixedBytes(self.to_vec())
+    }
+
+    fn to_json(&self) -> Value {
+        Value::String(format!("0x{}", hex::encode(self)))
+    }
+}
+
+impl Eip712Field for H256 {
+    const SOL_TYPE: &'static str = "bytes32";
+
+    fn encode_field(&self) -> Token {
+        Token::FixedBytes(self.as_bytes().to_vec())
+    }
+
+    fn to_json(&self) -> Value {
+        Value::String(format!("{:?}", self))
+    }
+}
+
+impl Eip712Field for u64 {
+    const SOL_TYPE: &'static str = "uint64";
+
+    fn encode_field(&self) -> Token {
+        Token::Uint(U256::from(*self))
+    }
+
+    fn to_json(&self) -> Value {
+        Value::from(*self)
+    }
+}
+
+// const string comparison, so typed_struct! can check declared types at compile time
+pub const fn same_str(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Implements `TypedStruct` from a single field list:
+///
+/// ```text
+/// eri_core::typed_struct!(Certificate, "Certificate", {
+///     unique_id: String => ("uniqueId", "string"),
+///     ...
+/// });
+/// ```
+///
+/// Each declared solidity type is checked against the Rust field type when compiling.
+#[macro_export]
+macro_rules! typed_struct {
+    (
+        $ty:ty, $primary:literal, {
+            $first:ident : $first_ty:ty => ($first_name:literal, $first_sol:literal)
+            $(, $field:ident : $field_ty:ty => ($name:literal, $sol:literal))* $(,)?
+        }
+    ) => {
+        const _: () = {
+            assert!(
+                $crate::typed_struct::same_str(
+                    <$first_ty as $crate::typed_struct::Eip712Field>::SOL_TYPE,
+                    $first_sol
+                ),
+                concat!("EIP-712 type of `", stringify!($first), "` does not match its Rust type")
+            );
+            $(
+                assert!(
+                    $crate::typed_struct::same_str(
+                        <$field_ty as $crate::typed_struct::Eip712Field>::SOL_TYPE,
+                        $sol
+                    ),
+                    concat!("EIP-712 type of `", stringify!($field), "` does not match its Rust type")
+                );
+            )*
+        };
+
+        impl $crate::typed_struct::TypedStruct for $ty {
+            const PRIMARY_TYPE: &'static str = $primary;
+            const ENCODED_TYPE: &'static str = concat!(
+                $primary, "(", $first_sol, " ", $first_name, $(",", $sol, " ", $name,)* ")"
+            );
+            const FIELDS: &'static [$crate::typed_struct::TypedField] = &[
+                $crate::typed_struct::TypedField { name: $first_name, kind: $first_sol },
+                $($crate::typed_struct::TypedField { name: $name, kind: $sol },)*
+            ];
+
+            fn encode_fields(&self) -> Vec<$crate::reexports::ethers_core::abi::Token> {
+                let first: &$first_ty = &self.$first;
+                $(let $field: &$field_ty = &self.$field;)*
+                vec![
+                    $crate::typed_struct::Eip712Field::encode_field(first),
+                    $($crate::typed_struct::Eip712Field::encode_field($field),)*
+                ]
+            }
+
+            fn field_values(&self) -> $crate::reexports::serde_json::Map<String, $crate::reexports::serde_json::Value> {
+                let mut values = $crate::reexports::serde_json::Map::new();
+                values.insert(
+                    $first_name.to_string(),
+                    $crate::typed_struct::Eip712Field::to_json(&self.$first),
+                );
+                $(
+                    values.insert(
+                        $name.to_string(),
+                        $crate::typed_struct::Eip712Field::to_json(&self.$field),
+                    );
+                )*
+                values
+            }
+        }
+    };
+}
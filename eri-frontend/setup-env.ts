@@ -5,8 +5,8 @@ import * as path from "path";
 const CONTRACT_ADDRESSES = {
   NEXT_PUBLIC_AUTHENTICITY: "0x98BC72046616b528D4Bc5bbcC7d99f82237A8B55",
   NEXT_PUBLIC_OWNERSHIP: "0x49e8207450dd0204Bb6a89A9edf7CE151cE58BBc",
-  NEXT_PUBLIC_SIGNING_DOMAIN: "ERI",
-  NEXT_PUBLIC_SIGNATURE_VERSION: "1",
+  // the ERI server, the Certificate type and signing domain are fetched from it
+  NEXT_PUBLIC_ERI_API_URL: "http://127.0.0.1:8080",
 };
 
 // Create .env.local file content
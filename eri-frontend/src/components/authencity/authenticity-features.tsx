@@ -46,7 +46,6 @@ export default function AuthenticityFeatures({
   const [signature, setSignature] = useState<string>("");
   const [veriSignature, setVeriSignature] = useState<string>("");
   const [qrCodeData, setQrCodeData] = useState<string>("");
-  const [chainId, setChainId] = useState<string>("");
   const [veriResult, setVeriResult] = useState<any>({});
   const [certificate, setCertificate] = useState<Certificate>({
     name: "iPhone 12",
@@ -196,7 +195,7 @@ export default function AuthenticityFeatures({
         owner: address!,
         metadata: certificate.metadata, // string
       };
-      const { domain, types, value } = signTypedData(cert, chainId);
+      const { domain, types, value } = await signTypedData(certWithHash);
       const inSign = await sContract.signTypedData(
         domain,
         types as unknown as Record<string, any[]>,
@@ -137,7 +137,7 @@ export default function AuthenticityOperations({
         owner: account!,
         metadata: certificate.metadata, // string
       };
-      const { domain, types, value } = signTypedData(cert, "1"); // Assuming chainId is "1" for mainnet
+      const { domain, types, value } = await signTypedData(certWithHash);
       // Get signer from provider
       const provider = new ethers.BrowserProvider(window.ethereum as any);
       const signer = await provider.getSigner();
@@ -1,44 +1,67 @@
-export function signTypedData(
-  certificate: Certificate,
-  chainId: string | number
-): TypedData {
-  // Use environment variables or fallback values
-  const SIGNING_DOMAIN = process.env.NEXT_PUBLIC_SIGNING_DOMAIN || "ERI";
-  const SIGNATURE_VERSION = process.env.NEXT_PUBLIC_SIGNATURE_VERSION || "1";
-  const AUTHENTICITY_ADDRESS =
-    process.env.NEXT_PUBLIC_AUTHENTICITY ||
-    "0x98BC72046616b528D4Bc5bbcC7d99f82237A8B55";
+const ERI_API_URL =
+  process.env.NEXT_PUBLIC_ERI_API_URL || "http://127.0.0.1:8080";
+
+let certificateType: Promise<CertificateType> | undefined;
+
+// The Certificate type and domain the server signs and verifies under, fetched once
+export function fetchCertificateType(): Promise<CertificateType> {
+  if (!certificateType) {
+    certificateType = fetch(`${ERI_API_URL}/certificate_type`)
+      .then((response) => {
+        if (!response.ok) {
+          throw new Error(
+            `GET /certificate_type failed with status ${response.status}`
+          );
+        }
+        return response.json() as Promise<CertificateType>;
+      })
+      .catch((error) => {
+        // let the next call retry instead of caching the failure
+        certificateType = undefined;
+        throw error;
+      });
+  }
+  return certificateType;
+}
+
+export async function signTypedData(
+  certificate: CertificateWithHash
+): Promise<TypedData> {
+  const { primary_type, types, domain } = await fetchCertificateType();
+  const primaryFields = types[primary_type];
+  if (
+    !primaryFields ||
+    !domain.name ||
+    !domain.version ||
+    !domain.chainId ||
+    !domain.verifyingContract
+  ) {
+    throw new Error("Server returned an incomplete Certificate type");
+  }
+
+  // one value per signed field, in the server's order and under the server's names
+  const fields: Record<string, unknown> = { ...certificate };
+  const value: TypedDataValue = {};
+  for (const field of primaryFields) {
+    const fieldValue = fields[field.name];
+    if (typeof fieldValue !== "string" && typeof fieldValue !== "number") {
+      throw new Error(`Certificate has no \`${field.name}\` field`);
+    }
+    value[field.name] =
+      field.type.startsWith("uint") && typeof fieldValue === "string"
+        ? parseInt(fieldValue)
+        : fieldValue;
+  }
 
   return {
-    types: {
-      Certificate: [
-        { name: "name", type: "string" },
-        { name: "uniqueId", type: "string" },
-        { name: "serial", type: "string" },
-        { name: "date", type: "uint256" },
-        { name: "owner", type: "address" },
-        { name: "metadataHash", type: "bytes32" },
-      ],
-    },
-    primaryType: "Certificate",
+    types: { [primary_type]: primaryFields },
+    primaryType: primary_type,
     domain: {
-      name: SIGNING_DOMAIN,
-      version: SIGNATURE_VERSION,
-      chainId: Number(chainId),
-      verifyingContract: AUTHENTICITY_ADDRESS,
-    },
-    value: {
-      name: certificate.name,
-      uniqueId: certificate.uniqueId,
-      serial: certificate.serial,
-      date:
-        typeof certificate.date === "string"
-          ? parseInt(certificate.date)
-          : certificate.date,
-      owner: certificate.owner,
-      metadataHash:
-        certificate.metadataHash ||
-        "0x0000000000000000000000000000000000000000000000000000000000000000",
+      name: domain.name,
+      version: domain.version,
+      chainId: Number(domain.chainId),
+      verifyingContract: domain.verifyingContract,
     },
+    value,
   };
 }
@@ -154,20 +154,29 @@ interface TypedDataDomain {
   verifyingContract: string;
 }
 
-interface TypedDataTypes {
-  Certificate: Array<{
-    name: string;
-    type: string;
-  }>;
+interface TypedDataField {
+  name: string;
+  type: string;
 }
 
-interface TypedDataValue {
-  name: string;
-  uniqueId: string;
-  serial: string;
-  date: number;
-  owner: string;
-  metadataHash: string;
+// Field lists come from the server's GET /certificate_type, not from here
+type TypedDataTypes = Record<string, TypedDataField[]>;
+
+type TypedDataValue = Record<string, string | number>;
+
+// GET /certificate_type
+interface CertificateType {
+  primary_type: string;
+  encoded_type: string;
+  type_hash: string;
+  types: TypedDataTypes;
+  domain: {
+    name: string | null;
+    version: string | null;
+    chainId: string | null;
+    verifyingContract: string | null;
+    salt: string | null;
+  };
 }
 
 interface TypedData {
@@ -207,8 +216,7 @@ interface ApiResponse<T = any> {
 interface EnvironmentVariables {
   NEXT_PUBLIC_AUTHENTICITY: string;
   NEXT_PUBLIC_OWNERSHIP: string;
-  NEXT_PUBLIC_SIGNING_DOMAIN: string;
-  NEXT_PUBLIC_SIGNATURE_VERSION: string;
+  NEXT_PUBLIC_ERI_API_URL: string;
 }
 
 // Utility Types
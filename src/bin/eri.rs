@@ -0,0 +1,6 @@
+use std::process::ExitCode;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<ExitCode> {
+    eri_server::cli::run().await
+}
@@ -0,0 +1,200 @@
+use crate::models::verification_model::ManufacturerCacheStats;
+use ethabi::ethereum_types::Address;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+// What getManufacturer answered for one address, `name` is None when it is not registered
+struct CachedManufacturer {
+    name: Option<String>,
+    fetched_at: Instant,
+}
+
+// In-process getManufacturer results keyed by address. Entries expire after the TTL
+// and are dropped early when a ManufacturerRegistered log for the address is seen
+pub struct ManufacturerCache {
+    ttl: Duration,
+    entries: RwLock<HashMap<Address, CachedManufacturer>>,
+    // bumped on every invalidation, so a lookup that raced one is not stored
+    generation: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ManufacturerCache {
+    /// A zero TTL turns caching off, every lookup then counts as a miss
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: RwLock::new(HashMap::new()),
+            generation: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// `Some(name)` on a fresh hit, where `name` is None for an unregistered address
+    pub fn get(&self, address: Address) -> Option<Option<String>> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        match entries.get(&address) {
+            Some(entry) if entry.fetched_at.elapsed() < self.ttl => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(entry.name.clone())
+            }
+            _ => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Read before fetching and hand to `insert`, to detect an invalidation in between
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    pub fn insert(&self, address: Address, name: Option<String>, generation: u64) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        if self.generation() != generation {
+            return;
+        }
+        entries.retain(|_, entry| entry.fetched_at.elapsed() < self.ttl);
+        entries.insert(
+            address,
+            CachedManufacturer {
+                name,
+                fetched_at: Instant::now(),
+            },
+        );
+    }
+
+    pub fn invalidate(&self, address: Address) {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        entries.remove(&address);
+    }
+
+    // after a reorg any registration may have been undone
+    pub fn clear(&self) {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        entries.clear();
+    }
+
+    pub fn stats(&self) -> ManufacturerCacheStats {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        ManufacturerCacheStats {
+            entries: entries.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            ttl_secs: self.ttl.as_secs(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::sleep;
+
+    const ACME: Address = Address::repeat_byte(0xac);
+    const OTHER: Address = Address::repeat_byte(0x0f);
+
+    fn counts(cache: &ManufacturerCache) -> (u64, u64, usize) {
+        let stats = cache.stats();
+        (stats.hits, stats.misses, stats.entries)
+    }
+
+    // a lookup that finds nothing and stores what the chain answered
+    fn fill(cache: &ManufacturerCache, address: Address, name: Option<&str>) {
+        assert_eq!(cache.get(address), None);
+        let generation = cache.generation();
+        cache.insert(address, name.map(str::to_string), generation);
+    }
+
+    #[test]
+    fn miss_then_hit() {
+        let cache = ManufacturerCache::new(Duration::from_secs(60));
+
+        fill(&cache, ACME, Some("Acme"));
+        fill(&cache, OTHER, None);
+
+        assert_eq!(cache.get(ACME), Some(Some("Acme".to_string())));
+        // an unregistered address is cached too
+        assert_eq!(cache.get(OTHER), Some(None));
+        assert_eq!(counts(&cache), (2, 2, 2));
+    }
+
+    #[test]
+    fn entries_expire_after_the_ttl() {
+        let cache = ManufacturerCache::new(Duration::from_millis(50));
+        fill(&cache, ACME, Some("Acme"));
+        assert_eq!(cache.get(ACME), Some(Some("Acme".to_string())));
+
+        sleep(Duration::from_millis(80));
+
+        assert_eq!(cache.get(ACME), None);
+        assert_eq!(counts(&cache), (1, 2, 1));
+
+        // expired entries are swept on the next insert
+        fill(&cache, OTHER, Some("Other"));
+        assert_eq!(counts(&cache), (1, 3, 1));
+    }
+
+    #[test]
+    fn invalidate_drops_only_that_address() {
+        let cache = ManufacturerCache::new(Duration::from_secs(60));
+        fill(&cache, ACME, None);
+        fill(&cache, OTHER, Some("Other"));
+
+        cache.invalidate(ACME);
+
+        assert_eq!(cache.get(ACME), None);
+        assert_eq!(cache.get(OTHER), Some(Some("Other".to_string())));
+        assert_eq!(counts(&cache), (1, 3, 1));
+    }
+
+    #[test]
+    fn lookup_racing_an_invalidation_is_not_stored() {
+        let cache = ManufacturerCache::new(Duration::from_secs(60));
+
+        // the lookup starts, then the registration log is seen before it answers
+        assert_eq!(cache.get(ACME), None);
+        let generation = cache.generation();
+        cache.invalidate(ACME);
+        cache.insert(ACME, None, generation);
+
+        assert_eq!(cache.get(ACME), None);
+        assert_eq!(counts(&cache), (0, 2, 0));
+
+        // the next lookup stores its answer again
+        fill(&cache, ACME, Some("Acme"));
+        assert_eq!(cache.get(ACME), Some(Some("Acme".to_string())));
+    }
+
+    #[test]
+    fn clear_also_discards_in_flight_lookups() {
+        let cache = ManufacturerCache::new(Duration::from_secs(60));
+        fill(&cache, ACME, Some("Acme"));
+        let generation = cache.generation();
+
+        cache.clear();
+        cache.insert(OTHER, Some("Other".to_string()), generation);
+
+        assert_eq!(counts(&cache), (0, 1, 0));
+    }
+
+    #[test]
+    fn zero_ttl_disables_caching() {
+        let cache = ManufacturerCache::new(Duration::ZERO);
+        fill(&cache, ACME, Some("Acme"));
+
+        assert_eq!(cache.get(ACME), None);
+        assert_eq!(counts(&cache), (0, 2, 0));
+    }
+}
@@ -0,0 +1,302 @@
+use crate::config::app_config::AppConfig;
+use crate::config::app_state::AppState;
+use crate::models::certificate_model::{CertificateData, QrPayload, QrQuery, SignedCertificate};
+use crate::services::create_eip712::typed_data;
+use crate::services::issue_certificate::{
+    BulkFormat, issue_stream, parse_rows, sign_certificate_data, validate_rows,
+};
+use crate::services::qr_code::render_qr;
+use crate::services::register_manufacturer::register_manufacturer;
+use crate::services::registry_snapshot::verify_with_snapshot;
+use crate::services::verify_authenticity::verify_certificate;
+use crate::signer::AppSigner;
+use crate::signer::remote::serve_stand_in;
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use dotenv::dotenv;
+use eri_core::SignedRegistrySnapshot;
+use ethers::types::Address;
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
+use std::fs;
+use std::io::{BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::pin::pin;
+use std::process::ExitCode;
+use validator::Validate;
+
+/// Sign, verify and register ERI certificates without running the HTTP server.
+///
+/// Reads the same eri.toml / environment configuration as the server.
+#[derive(Parser)]
+#[command(name = "eri", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Sign a certificate (the /generate_signature body) and print the signature
+    Sign {
+        /// Certificate JSON file, `-` for stdin
+        #[arg(default_value = "-")]
+        input: String,
+    },
+    /// Verify a signed certificate and print the verdict, exits with 2 when it is not authentic
+    Verify {
+        /// Signed certificate JSON file, `-` for stdin
+        #[arg(default_value = "-")]
+        input: String,
+        /// Verify against this registry snapshot (GET /registry_snapshot) instead of the
+        /// chain. Needs no RPC node and no configuration
+        #[arg(long, requires = "trusted_signer")]
+        snapshot: Option<String>,
+        /// Address the snapshot must be signed by, the ERI server's wallet
+        #[arg(long)]
+        trusted_signer: Option<Address>,
+        /// How old the snapshot may be before the verdict is flagged stale, defaults to a day
+        #[arg(long)]
+        max_age_secs: Option<u64>,
+    },
+    /// Register the configured wallet as a manufacturer
+    RegisterManufacturer { name: String },
+    /// Print the EIP-712 typed data a wallet would sign for a certificate
+    TypedData {
+        /// Certificate JSON file, `-` for stdin
+        #[arg(default_value = "-")]
+        input: String,
+    },
+    /// Serve the configured key over the remote signer JSON-RPC methods, a local
+    /// stand-in for testing the `remote` wallet backend
+    ServeSigner {
+        #[arg(long, default_value = "127.0.0.1:8550")]
+        listen: String,
+    },
+    /// Issue certificates in bulk from CSV or JSON Lines rows, printing one JSON line per row.
+    /// Exits with 2 when any row is invalid or fails to sign
+    Issue {
+        /// CSV or JSON Lines file, `-` for stdin
+        #[arg(default_value = "-")]
+        input: String,
+        /// Input format, by default CSV for .csv files and JSON Lines otherwise
+        #[arg(long, value_enum)]
+        format: Option<BulkFormat>,
+        #[command(flatten)]
+        qr: QrQuery,
+        /// Write the results here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Render a signed certificate as a QR code, SVG by default
+    Qr {
+        /// Signed certificate JSON file, `-` for stdin
+        #[arg(default_value = "-")]
+        input: String,
+        /// Write the QR code here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        qr: QrQuery,
+    },
+}
+
+fn read_input(input: &str) -> anyhow::Result<String> {
+    if input == "-" {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .context("failed to read stdin")?;
+        Ok(content)
+    } else {
+        fs::read_to_string(input).with_context(|| format!("failed to read {}", input))
+    }
+}
+
+fn read_json<T: DeserializeOwned>(input: &str) -> anyhow::Result<T> {
+    let content = read_input(input)?;
+    serde_json::from_str(&content).with_context(|| format!("{} is not valid JSON input", input))
+}
+
+// What a command needs from the chain, so read-only commands never touch the wallet
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Access {
+    /// Nothing, the command works offline or loads what it needs itself
+    Offline,
+    /// RPC reads only, no key is loaded
+    Read,
+    /// The wallet and signing keys, to sign certificates or send transactions
+    Sign,
+}
+
+impl Command {
+    fn access(&self) -> Access {
+        match self {
+            Command::Verify {
+                snapshot: Some(_), ..
+            }
+            | Command::ServeSigner { .. }
+            | Command::Qr { .. } => Access::Offline,
+            Command::Verify { .. } | Command::TypedData { .. } => Access::Read,
+            Command::Sign { .. } | Command::RegisterManufacturer { .. } | Command::Issue { .. } => {
+                Access::Sign
+            }
+        }
+    }
+}
+
+// Same client setup as the server, minus the event indexer. The keystore is only
+// decrypted, and its passphrase only prompted for, when the command signs
+async fn app_state(access: Access) -> anyhow::Result<Option<AppState>> {
+    if access == Access::Offline {
+        return Ok(None);
+    }
+    dotenv().ok();
+    let mut config = match access {
+        Access::Sign => AppConfig::load()?,
+        _ => AppConfig::load_read_only()?,
+    };
+
+    // nothing is indexed from the CLI, don't open the event database
+    config.indexer.enabled = false;
+    Ok(Some(AppState::init_app_state(config).await?))
+}
+
+pub async fn run() -> anyhow::Result<ExitCode> {
+    let cli = Cli::parse();
+    let state = app_state(cli.command.access()).await?;
+    // set for every command that is not Access::Offline
+    let connected = || {
+        state
+            .clone()
+            .context("command was started without chain access")
+    };
+
+    match cli.command {
+        Command::Sign { input } => {
+            let cert: CertificateData = read_json(&input)?;
+            let (signed, _, _) = sign_certificate_data(&connected()?, cert).await?;
+            println!("{}", signed.signature);
+        }
+        Command::Verify {
+            input,
+            snapshot: Some(snapshot),
+            trusted_signer: Some(trusted_signer),
+            max_age_secs,
+        } => {
+            let cert: SignedCertificate = read_json(&input)?;
+            let snapshot: SignedRegistrySnapshot = read_json(&snapshot)?;
+            let result = verify_with_snapshot(&cert, &snapshot, trusted_signer, max_age_secs)?;
+            println!("{}", serde_json::to_string_pretty(&result)?);
+            if result.stale {
+                eprintln!(
+                    "⚠️ The snapshot is {}s old, manufacturers registered since are not known",
+                    result.snapshot_age_secs
+                );
+            }
+            if !result.result.authentic {
+                return Ok(ExitCode::from(2));
+            }
+        }
+        Command::Verify { input, .. } => {
+            let cert: SignedCertificate = read_json(&input)?;
+            let result = verify_certificate(&connected()?, &cert).await?;
+            println!("{}", serde_json::to_string_pretty(&result)?);
+            if !result.authentic {
+                return Ok(ExitCode::from(2));
+            }
+        }
+        Command::RegisterManufacturer { name } => {
+            let (address, name) = register_manufacturer(&connected()?, name).await?;
+            println!(
+                "Manufacturer Address: {:?}, Manufacturer Name: {:?}",
+                address, name
+            );
+        }
+        Command::TypedData { input } => {
+            let cert: CertificateData = read_json(&input)?;
+            let typed_data = typed_data(&connected()?, cert)?;
+            println!("{}", serde_json::to_string_pretty(&typed_data)?);
+        }
+        Command::ServeSigner { listen } => {
+            dotenv().ok();
+            let config = AppConfig::load()?;
+            // transactions carry their own chain id, so this one only matters for the log line
+            let AppSigner::Local(wallet) = AppSigner::from_config(&config.wallet, 1).await? else {
+                anyhow::bail!(
+                    "serve-signer needs a key or keystore wallet, not another remote signer"
+                );
+            };
+            serve_stand_in(wallet, &listen).await?;
+        }
+        Command::Issue {
+            input,
+            format,
+            qr,
+            output,
+        } => {
+            let format = format.unwrap_or_else(|| BulkFormat::from_path(&input));
+            let rows = parse_rows(&read_input(&input)?, format)?;
+            let state = connected()?;
+
+            let rows = match validate_rows(&state, rows) {
+                Ok(rows) => rows,
+                Err(errors) => {
+                    for error in &errors {
+                        eprintln!("line {}: {}", error.line, error.message);
+                    }
+                    eprintln!("{} invalid rows, nothing was signed", errors.len());
+                    return Ok(ExitCode::from(2));
+                }
+            };
+
+            let mut out: Box<dyn Write> = match &output {
+                Some(path) => Box::new(BufWriter::new(
+                    fs::File::create(path)
+                        .with_context(|| format!("failed to create {}", path.display()))?,
+                )),
+                None => Box::new(std::io::stdout().lock()),
+            };
+
+            let total = rows.len();
+            let mut failed = 0;
+            let mut lines = pin!(issue_stream(state, rows, qr));
+            while let Some(line) = lines.next().await {
+                if let Some(error) = &line.error {
+                    failed += 1;
+                    eprintln!("line {}: {}", line.line, error.message);
+                }
+                writeln!(out, "{}", serde_json::to_string(&line)?)?;
+            }
+            out.flush()?;
+
+            eprintln!("Issued {} of {} certificates", total - failed, total);
+            if failed > 0 {
+                return Ok(ExitCode::from(2));
+            }
+        }
+        Command::Qr { input, output, qr } => {
+            let cert: SignedCertificate = read_json(&input)?;
+            cert.validate().context("invalid certificate")?;
+            // only links need the configuration, and only its server section
+            let verify_url = match qr.qr_payload {
+                QrPayload::Url => {
+                    dotenv().ok();
+                    AppConfig::load_server()?.verify_url
+                }
+                _ => None,
+            };
+            let rendered = render_qr(&cert, &qr, verify_url.as_deref())?;
+            match output {
+                Some(path) => fs::write(&path, &rendered.bytes)
+                    .with_context(|| format!("failed to write {}", path.display()))?,
+                None if rendered.format.is_binary() => {
+                    std::io::stdout().lock().write_all(&rendered.bytes)?
+                }
+                None => println!("{}", rendered.into_text()),
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
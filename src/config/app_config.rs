@@ -0,0 +1,424 @@
+use crate::models::certificate_model::CERTIFICATE_TYPE;
+use anyhow::{Context, anyhow, bail};
+use ethabi::ethereum_types::Address;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const DEFAULT_CONFIG_PATH: &str = "eri.toml";
+
+// Validated configuration, loaded once at startup and shared through AppState
+#[derive(Clone)]
+pub struct AppConfig {
+    pub server: ServerConfig,
+    pub chain: ChainConfig,
+    pub signing: SigningConfig,
+    pub wallet: WalletConfig,
+    pub indexer: IndexerConfig,
+    pub cache: CacheConfig,
+    pub manufacturer_keys: Vec<ManufacturerKeyConfig>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    /// Public address of the GET /v/{payload} route, the host `url` QR codes point at
+    pub verify_url: Option<String>,
+}
+
+impl ServerConfig {
+    pub fn bind_address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ChainConfig {
+    pub rpc_url: String,
+    /// When set, startup fails if the RPC node reports a different chain
+    pub chain_id: Option<u64>,
+    pub authenticity_contract: Address,
+    pub ownership_contract: Address,
+}
+
+// Must match the constructor arguments the Authenticity contract was deployed with
+#[derive(Clone, Debug)]
+pub struct SigningConfig {
+    pub domain: String,
+    pub version: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct IndexerConfig {
+    pub enabled: bool,
+    /// SQLite file the followed contract events are stored in
+    pub database: String,
+    /// Usually the block the contracts were deployed at
+    pub start_block: u64,
+    pub batch_size: u64,
+    pub poll_interval_secs: u64,
+}
+
+// A certificate signing key for one manufacturer, besides the server wallet
+#[derive(Clone)]
+pub struct ManufacturerKeyConfig {
+    /// The name the manufacturer is registered under on the Authenticity contract
+    pub manufacturer: String,
+    pub wallet: WalletConfig,
+}
+
+#[derive(Clone, Debug)]
+pub struct CacheConfig {
+    /// How long a getManufacturer answer is reused, 0 turns the cache off
+    pub manufacturer_ttl_secs: u64,
+}
+
+// Where the server's signing key lives
+#[derive(Clone)]
+pub enum WalletConfig {
+    /// A plaintext private key, meant for development
+    Key { private_key: String },
+    /// An encrypted JSON keystore, the passphrase is read from a file or prompted for
+    Keystore {
+        path: String,
+        passphrase_file: Option<String>,
+    },
+    /// A JSON-RPC signer that holds the key for `address`
+    Remote { url: String, address: Address },
+    /// No wallet, for clients that only read the chain, see `AppConfig::load_read_only`
+    ReadOnly,
+}
+
+// Shape of the TOML file, every key is optional so env vars can fill the gaps
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    server: FileServer,
+    chain: FileChain,
+    signing: FileSigning,
+    wallet: FileWallet,
+    indexer: FileIndexer,
+    cache: FileCache,
+    manufacturer_keys: Vec<FileManufacturerKey>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileServer {
+    host: Option<String>,
+    port: Option<u16>,
+    verify_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileChain {
+    rpc_url: Option<String>,
+    chain_id: Option<u64>,
+    authenticity_contract: Option<String>,
+    ownership_contract: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileSigning {
+    domain: Option<String>,
+    version: Option<String>,
+    certificate: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileIndexer {
+    enabled: Option<bool>,
+    database: Option<String>,
+    start_block: Option<u64>,
+    batch_size: Option<u64>,
+    poll_interval_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileCache {
+    manufacturer_ttl_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileManufacturerKey {
+    manufacturer: Option<String>,
+    wallet: FileWallet,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileWallet {
+    backend: Option<String>,
+    private_key: Option<String>,
+    keystore: Option<String>,
+    passphrase_file: Option<String>,
+    remote_url: Option<String>,
+    remote_address: Option<String>,
+}
+
+impl std::fmt::Debug for FileWallet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileWallet").finish_non_exhaustive()
+    }
+}
+
+impl AppConfig {
+    /// Reads `ERI_CONFIG` (default `eri.toml`), then applies environment overrides
+    pub fn load() -> anyhow::Result<AppConfig> {
+        Self::read(true)
+    }
+
+    /// Like `load`, but the wallet and manufacturer keys are neither required nor read,
+    /// so a keystore is never decrypted. Every signature or transaction will fail
+    pub fn load_read_only() -> anyhow::Result<AppConfig> {
+        Self::read(false)
+    }
+
+    /// Only the `[server]` section, for offline commands that build verification links.
+    /// The chain and wallet settings are neither required nor read
+    pub fn load_server() -> anyhow::Result<ServerConfig> {
+        Self::read_file()?.server.validate()
+    }
+
+    fn read(with_wallet: bool) -> anyhow::Result<AppConfig> {
+        Self::read_file()?.validate(with_wallet)
+    }
+
+    fn read_file() -> anyhow::Result<FileConfig> {
+        let explicit_path = env::var("ERI_CONFIG").ok();
+        let path = explicit_path.as_deref().unwrap_or(DEFAULT_CONFIG_PATH);
+
+        let mut file = if Path::new(path).exists() {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("failed to read config file {}", path))?;
+            toml::from_str::<FileConfig>(&content)
+                .with_context(|| format!("invalid config file {}", path))?
+        } else if explicit_path.is_some() {
+            bail!("config file {} (from ERI_CONFIG) does not exist", path);
+        } else {
+            FileConfig::default()
+        };
+
+        file.apply_env()?;
+        Ok(file)
+    }
+}
+
+fn env_override(target: &mut Option<String>, key: &str) {
+    if let Ok(value) = env::var(key) {
+        *target = Some(value);
+    }
+}
+
+fn parse_env<T: std::str::FromStr>(target: &mut Option<T>, key: &str) -> anyhow::Result<()> {
+    if let Ok(value) = env::var(key) {
+        let parsed = value
+            .parse()
+            .map_err(|_| anyhow!("{} has an invalid value {:?}", key, value))?;
+        *target = Some(parsed);
+    }
+    Ok(())
+}
+
+fn required(value: Option<String>, key: &str, env_key: &str) -> anyhow::Result<String> {
+    match value {
+        Some(value) if !value.trim().is_empty() => Ok(value),
+        _ => bail!("missing configuration: set `{}` in the config file or {}", key, env_key),
+    }
+}
+
+fn parse_address(value: Option<String>, key: &str, env_key: &str) -> anyhow::Result<Address> {
+    let value = required(value, key, env_key)?;
+    value
+        .parse()
+        .map_err(|_| anyhow!("`{}` is not a valid address: {:?}", key, value))
+}
+
+impl FileServer {
+    fn validate(self) -> anyhow::Result<ServerConfig> {
+        // links are built as `{verify_url}/v/{payload}`
+        let verify_url = match self.verify_url {
+            Some(url) if url.starts_with("https://") || url.starts_with("http://") => {
+                Some(url.trim_end_matches('/').to_string())
+            }
+            Some(url) if url.trim().is_empty() => None,
+            Some(url) => bail!("`server.verify_url` must be an http(s) URL, got {:?}", url),
+            None => None,
+        };
+        Ok(ServerConfig {
+            host: self.host.unwrap_or_else(|| "127.0.0.1".to_string()),
+            port: self.port.unwrap_or(8080),
+            verify_url,
+        })
+    }
+}
+
+impl FileWallet {
+    // without an explicit backend, the first of keystore, remote signer and raw key that is set wins
+    fn validate(self) -> anyhow::Result<WalletConfig> {
+        let backend = match self.backend.as_deref() {
+            Some(backend) => backend,
+            None if self.keystore.is_some() => "keystore",
+            None if self.remote_url.is_some() => "remote",
+            None => "key",
+        };
+
+        let wallet = match backend {
+            "key" => WalletConfig::Key {
+                private_key: required(self.private_key, "wallet.private_key", "PRIVATE_KEY")?,
+            },
+            "keystore" => WalletConfig::Keystore {
+                path: required(self.keystore, "wallet.keystore", "KEYSTORE_PATH")?,
+                passphrase_file: self.passphrase_file,
+            },
+            "remote" => WalletConfig::Remote {
+                url: required(self.remote_url, "wallet.remote_url", "REMOTE_SIGNER_URL")?,
+                address: parse_address(
+                    self.remote_address,
+                    "wallet.remote_address",
+                    "REMOTE_SIGNER_ADDRESS",
+                )?,
+            },
+            other => bail!(
+                "`wallet.backend` must be one of \"key\", \"keystore\" or \"remote\", got {:?}",
+                other
+            ),
+        };
+        Ok(wallet)
+    }
+}
+
+impl FileConfig {
+    // Env var names are the ones the server has always used, so existing .env files keep working
+    fn apply_env(&mut self) -> anyhow::Result<()> {
+        env_override(&mut self.server.host, "SERVER_HOST");
+        parse_env(&mut self.server.port, "SERVER_PORT")?;
+        env_override(&mut self.server.verify_url, "VERIFY_URL");
+
+        env_override(&mut self.chain.rpc_url, "BASE_URL");
+        parse_env(&mut self.chain.chain_id, "CHAIN_ID")?;
+        env_override(&mut self.chain.authenticity_contract, "CONTRACT_ADDRESS");
+        env_override(&mut self.chain.ownership_contract, "OWNERSHIP_ADDRESS");
+
+        env_override(&mut self.signing.domain, "SIGNING_DOMAIN");
+        env_override(&mut self.signing.version, "SIGNATURE_VERSION");
+        env_override(&mut self.signing.certificate, "CERTIFICATE");
+
+        env_override(&mut self.wallet.backend, "WALLET_BACKEND");
+        env_override(&mut self.wallet.private_key, "PRIVATE_KEY");
+        env_override(&mut self.wallet.keystore, "KEYSTORE_PATH");
+        env_override(&mut self.wallet.passphrase_file, "KEYSTORE_PASSPHRASE_FILE");
+        env_override(&mut self.wallet.remote_url, "REMOTE_SIGNER_URL");
+        env_override(&mut self.wallet.remote_address, "REMOTE_SIGNER_ADDRESS");
+
+        parse_env(&mut self.indexer.enabled, "INDEXER_ENABLED")?;
+        env_override(&mut self.indexer.database, "INDEXER_DATABASE");
+        parse_env(&mut self.indexer.start_block, "INDEXER_START_BLOCK")?;
+
+        parse_env(&mut self.cache.manufacturer_ttl_secs, "MANUFACTURER_CACHE_TTL_SECS")?;
+        Ok(())
+    }
+
+    fn validate(self, with_wallet: bool) -> anyhow::Result<AppConfig> {
+        let server = self.server.validate()?;
+
+        let chain = ChainConfig {
+            rpc_url: required(self.chain.rpc_url, "chain.rpc_url", "BASE_URL")?,
+            chain_id: self.chain.chain_id,
+            authenticity_contract: parse_address(
+                self.chain.authenticity_contract,
+                "chain.authenticity_contract",
+                "CONTRACT_ADDRESS",
+            )?,
+            ownership_contract: parse_address(
+                self.chain.ownership_contract,
+                "chain.ownership_contract",
+                "OWNERSHIP_ADDRESS",
+            )?,
+        };
+
+        let signing = SigningConfig {
+            domain: required(self.signing.domain, "signing.domain", "SIGNING_DOMAIN")?,
+            version: required(self.signing.version, "signing.version", "SIGNATURE_VERSION")?,
+        };
+
+        // the type string is fixed by the Certificate struct, a configured one is only checked
+        if let Some(certificate) = self.signing.certificate
+            && certificate != CERTIFICATE_TYPE
+        {
+            bail!(
+                "`signing.certificate` ({:?}) does not match the Certificate struct ({:?})",
+                certificate,
+                CERTIFICATE_TYPE
+            );
+        }
+
+        let wallet = if with_wallet {
+            self.wallet.validate()?
+        } else {
+            WalletConfig::ReadOnly
+        };
+
+        let indexer = IndexerConfig {
+            // opt-in, on an existing deployment it starts a scan from `start_block`
+            enabled: self.indexer.enabled.unwrap_or(false),
+            database: self
+                .indexer
+                .database
+                .unwrap_or_else(|| "eri-events.db".to_string()),
+            start_block: self.indexer.start_block.unwrap_or(0),
+            batch_size: self.indexer.batch_size.unwrap_or(2000),
+            poll_interval_secs: self.indexer.poll_interval_secs.unwrap_or(5),
+        };
+        if indexer.batch_size == 0 || indexer.poll_interval_secs == 0 {
+            bail!("`indexer.batch_size` and `indexer.poll_interval_secs` must be greater than 0");
+        }
+        if indexer.enabled && self.indexer.start_block.is_none() {
+            eprintln!(
+                "⚠️ indexer.start_block (INDEXER_START_BLOCK) is not set, indexing the full history from block 0"
+            );
+        }
+
+        let cache = CacheConfig {
+            manufacturer_ttl_secs: self.cache.manufacturer_ttl_secs.unwrap_or(300),
+        };
+
+        // a read-only config never signs, its keys are not even validated
+        let manufacturer_keys = self
+            .manufacturer_keys
+            .into_iter()
+            .filter(|_| with_wallet)
+            .map(|key| {
+                let manufacturer = match key.manufacturer {
+                    Some(name) if !name.trim().is_empty() => name,
+                    _ => bail!("every `[[manufacturer_keys]]` entry needs a `manufacturer` name"),
+                };
+                let wallet = key
+                    .wallet
+                    .validate()
+                    .with_context(|| format!("invalid signing key for {:?}", manufacturer))?;
+                Ok(ManufacturerKeyConfig {
+                    manufacturer,
+                    wallet,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(AppConfig {
+            server,
+            chain,
+            signing,
+            wallet,
+            indexer,
+            cache,
+            manufacturer_keys,
+        })
+    }
+}
@@ -1,14 +1,30 @@
 use crate::config::swagger_config::ApiDoc;
 use crate::models::router_path::RouterPath;
-use crate::services::verify_authenticity::verify_authenticity;
-use crate::services::create_eip712::create_certificate;
-use crate::services::qr_code::generate_qr_code;
+use crate::services::verify_authenticity::{
+    manufacturer_cache_stats, verify_authenticity, verify_authenticity_batch,
+};
+use crate::services::create_eip712::{certificate_type, create_certificate};
+use crate::services::qr_code::{decode_qr_code_payload, generate_qr_code};
+use crate::services::verify_qr::{verify_link, verify_qr_image};
+use crate::services::label_sheet::generate_label_sheet;
+use crate::services::issue_certificate::{issue_certificate, issue_certificates_bulk};
+use crate::services::certificate_batch::{issue_certificate_batch, verify_batched_certificate};
+use crate::services::item_history::item_history;
+use crate::services::registry_snapshot::{registry_snapshot, verify_offline};
 use crate::services::other_tests::{
     generate_signature, get_owner, manufacturer_registers,
     verify_signature,
 };
+use crate::services::change_ownership::{
+    claim_ownership, generate_ownership_code, revoke_ownership_code,
+};
+use crate::services::ownership::{
+    get_all_my_items, get_item, get_temp_owner, get_user, is_owner, user_registers,
+    verify_ownership,
+};
 use crate::config::app_state::AppState;
 use axum::Router;
+use axum::extract::DefaultBodyLimit;
 use axum::routing::{get, post};
 use ethers::contract::abigen;
 use tower_http::cors::CorsLayer;
@@ -22,15 +38,89 @@ abigen!(
     event_derives(serde::Deserialize, serde::Serialize)
 );
 
+abigen!(
+    Ownership,
+    "./hh-artifacts/contracts/Ownership.sol/Ownership.json",
+    event_derives(serde::Deserialize, serde::Serialize)
+);
+
+// custom errors shared by Authenticity, Ownership and OwnershipLib (EriErrors.sol),
+// plus the OpenZeppelin ECDSA errors raised while recovering a signer
+abigen!(
+    EriErrors,
+    r#"[
+        error ONLY_OWNER(address)
+        error ALREADY_REGISTERED(address)
+        error ADDRESS_ZERO(address)
+        error CODE_ALREADY_GENERATED()
+        error UNAUTHORIZED(address)
+        error ITEM_DOESNT_EXIST(string)
+        error DOES_NOT_EXIST()
+        error CONTRACT_DOEST_NOT_EXIST()
+        error NAME_ALREADY_EXIST(string)
+        error INVALID_SIGNATURE()
+        error ITEM_CLAIMED_ALREADY(string)
+        error ITEM_NOT_CLAIMED_YET()
+        error NOT_REGISTERED(address)
+        error NAME_NOT_AVAILABLE(string)
+        error USER_DOES_NOT_EXIST(address)
+        error CANNOT_GENERATE_CODE_FOR_YOURSELF(address)
+        error USERNAME_MUST_BE_AT_LEAST_3_LETTERS()
+        error INVALID_MANUFACTURER_NAME(string)
+        error AUTHENTICITY_NOT_SET()
+        error ECDSAInvalidSignature()
+        error ECDSAInvalidSignatureLength(uint256)
+        error ECDSAInvalidSignatureS(bytes32)
+    ]"#
+);
+
 pub fn paths(state: AppState, path: RouterPath) -> Router {
     let app = Router::new()
         .route(&path.generate_signature, post(generate_signature))
         .route(&path.verify_authenticity, post(verify_authenticity))
+        .route(&path.verify_authenticity_batch, post(verify_authenticity_batch))
+        // phone photos run to a few megabytes, over axum's 2 MB default
+        .route(
+            &path.verify_qr,
+            post(verify_qr_image).layer(DefaultBodyLimit::max(20 * 1024 * 1024)),
+        )
+        .route(&path.verify_link, get(verify_link))
+        .route(&path.manufacturer_cache, get(manufacturer_cache_stats))
         .route(&path.sign_up, post(manufacturer_registers))
         .route(&path.get_owner, get(get_owner))
         .route(&path.verify_signature, post(verify_signature))
         .route(&path.create_certificate, post(create_certificate))
+        .route(&path.certificate_type, get(certificate_type))
         .route(&path.qr_code, post(generate_qr_code))
+        .route(&path.decode_qr_payload, post(decode_qr_code_payload))
+        .route(
+            &path.label_sheets,
+            post(generate_label_sheet).layer(DefaultBodyLimit::max(16 * 1024 * 1024)),
+        )
+        .route(&path.issue_certificate, post(issue_certificate))
+        // a shift's worth of rows is well over axum's 2 MB default
+        .route(
+            &path.issue_certificates_bulk,
+            post(issue_certificates_bulk).layer(DefaultBodyLimit::max(64 * 1024 * 1024)),
+        )
+        .route(
+            &path.certificate_batches,
+            post(issue_certificate_batch).layer(DefaultBodyLimit::max(64 * 1024 * 1024)),
+        )
+        .route(&path.verify_batched_certificate, post(verify_batched_certificate))
+        .route(&path.user_registers, post(user_registers))
+        .route(&path.get_user, get(get_user))
+        .route(&path.get_all_my_items, get(get_all_my_items))
+        .route(&path.get_item, get(get_item))
+        .route(&path.verify_ownership, get(verify_ownership))
+        .route(&path.is_owner, get(is_owner))
+        .route(&path.get_temp_owner, get(get_temp_owner))
+        .route(&path.generate_ownership_code, post(generate_ownership_code))
+        .route(&path.claim_ownership, post(claim_ownership))
+        .route(&path.revoke_ownership_code, post(revoke_ownership_code))
+        .route(&path.item_history, get(item_history))
+        .route(&path.registry_snapshot, get(registry_snapshot))
+        .route(&path.verify_offline, post(verify_offline))
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .with_state(state)
         .layer(CorsLayer::permissive()); // Optional: Enable CORS
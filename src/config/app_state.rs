@@ -1,64 +1,164 @@
-use anyhow::Error;
+use crate::cache::ManufacturerCache;
+use crate::config::app_config::{AppConfig, WalletConfig};
+use crate::error::ApiError;
+use crate::indexer::store::EventStore;
+use crate::signer::AppSigner;
+use crate::signer::registry::KeyRegistry;
+use anyhow::{Context, Error};
+use eri_core::certificate_domain;
 use ethabi::ethereum_types::Address;
 use ethers::middleware::{Middleware, SignerMiddleware};
-use ethers::prelude::{Http, LocalWallet, Provider};
-use ethers::signers::Signer;
-use std::env;
+use ethers::prelude::{Http, Provider};
+use ethers::types::transaction::eip712::EIP712Domain;
 use std::sync::Arc;
 use std::time::Duration;
 
+// Provider plus whichever signer backend is configured
+pub type EthClient = SignerMiddleware<Provider<Http>, AppSigner>;
+
 #[derive(Clone)]
 pub struct AppState {
-    pub eth_client: Arc<SignerMiddleware<Provider<Http>, LocalWallet>>,
-    pub authenticity_contract: Address
+    pub eth_client: Arc<EthClient>,
+    pub authenticity_contract: Address,
+    pub ownership_contract: Address,
+    pub chain_id: u64,
+    pub config: Arc<AppConfig>,
+    /// only opened when the indexer is enabled
+    pub events: Option<Arc<EventStore>>,
+    pub manufacturers: Arc<ManufacturerCache>,
+    /// certificate signing keys, by manufacturer
+    pub keys: Arc<KeyRegistry>,
 }
 
 impl AppState {
-    pub async fn init_app_state() -> anyhow::Result<AppState, Error> {
+    pub async fn init_app_state(config: AppConfig) -> anyhow::Result<AppState, Error> {
         // Initialize Ethereum client
-        let rpc_url = env::var("BASE_URL")?;
-        let private_key = env::var("PRIVATE_KEY")?;
+        let provider = Provider::<Http>::try_from(config.chain.rpc_url.as_str())
+            .with_context(|| format!("invalid rpc url {:?}", config.chain.rpc_url))?
+            .interval(Duration::from_millis(1000));
+        let chain_id = provider
+            .get_chainid()
+            .await
+            .context("could not reach the RPC node to read its chain id")?
+            .as_u64();
 
-        let originality_factory: Address = env::var("CONTRACT_ADDRESS")?
-            .parse()
-            .map_err(|_| anyhow::anyhow!("Invalid contract address"))?;
+        if let Some(expected) = config.chain.chain_id
+            && expected != chain_id
+        {
+            anyhow::bail!(
+                "configured chain id {} does not match the RPC node's chain id {}",
+                expected,
+                chain_id
+            );
+        }
 
-        let provider = Provider::<Http>::try_from(&rpc_url)?.interval(Duration::from_millis(1000));
-        let chain_id = provider.get_chainid().await?.as_u64();
+        let signer = AppSigner::from_config(&config.wallet, chain_id).await?;
+        let eth_client = Arc::new(SignerMiddleware::new(provider, signer));
+        // a read-only client has no keys to check against the registry
+        let keys = match config.wallet {
+            WalletConfig::ReadOnly => KeyRegistry::empty(),
+            _ => {
+                KeyRegistry::load(
+                    &eth_client,
+                    config.chain.authenticity_contract,
+                    chain_id,
+                    &config.manufacturer_keys,
+                )
+                .await?
+            }
+        };
 
-        let wallet = private_key.parse::<LocalWallet>()?.with_chain_id(chain_id);
-        let eth_client = Arc::new(SignerMiddleware::new(provider, wallet.clone()));
+        let events = if config.indexer.enabled {
+            Some(Arc::new(EventStore::open(&config.indexer.database)?))
+        } else {
+            None
+        };
+        let manufacturers = ManufacturerCache::new(Duration::from_secs(
+            config.cache.manufacturer_ttl_secs,
+        ));
 
         // Initialize app state
         let state = AppState {
             eth_client, //wallet address could be gotten from eth_client.signer.address()
-            authenticity_contract: originality_factory,
+            authenticity_contract: config.chain.authenticity_contract,
+            ownership_contract: config.chain.ownership_contract,
+            chain_id,
+            config: Arc::new(config),
+            events,
+            manufacturers: Arc::new(manufacturers),
+            keys: Arc::new(keys),
         };
 
         Ok(state)
     }
-}
 
-// pub async fn init_app_state() -> anyhow::Result<AppState, Error> {
-//     // Initialize Ethereum client
-//     let rpc_url = env::var("BASE_URL")?;
-//     let private_key = env::var("PRIVATE_KEY")?;
-// 
-//     let originality_factory: Address = env::var("CONTRACT_ADDRESS")?
-//         .parse()
-//         .map_err(|_| anyhow::anyhow!("Invalid contract address"))?;
-// 
-//     let provider = Provider::<Http>::try_from(&rpc_url)?.interval(Duration::from_millis(1000));
-//     let chain_id = provider.get_chainid().await?.as_u64();
-// 
-//     let wallet = private_key.parse::<LocalWallet>()?.with_chain_id(chain_id);
-//     let eth_client = Arc::new(SignerMiddleware::new(provider, wallet.clone()));
-// 
-//     // Initialize app state
-//     let state = AppState {
-//         eth_client, //wallet address could be gotten from eth_client.signer.address()
-//         authenticity_contract: originality_factory,
-//     };
-// 
-//     Ok(state)
-// }
+    // A state whose RPC node is never reached, for tests of everything before the chain
+    #[cfg(test)]
+    pub fn for_tests(keys: KeyRegistry) -> AppState {
+        use crate::config::app_config::{
+            CacheConfig, ChainConfig, IndexerConfig, ServerConfig, SigningConfig,
+        };
+
+        let config = AppConfig {
+            server: ServerConfig {
+                host: "127.0.0.1".to_string(),
+                port: 8080,
+                verify_url: None,
+            },
+            chain: ChainConfig {
+                rpc_url: "http://127.0.0.1:1".to_string(),
+                chain_id: Some(31337),
+                authenticity_contract: Address::repeat_byte(0x11),
+                ownership_contract: Address::repeat_byte(0x22),
+            },
+            signing: SigningConfig {
+                domain: "ERI".to_string(),
+                version: "1".to_string(),
+            },
+            wallet: WalletConfig::ReadOnly,
+            indexer: IndexerConfig {
+                enabled: false,
+                database: String::new(),
+                start_block: 0,
+                batch_size: 1,
+                poll_interval_secs: 1,
+            },
+            cache: CacheConfig {
+                manufacturer_ttl_secs: 60,
+            },
+            manufacturer_keys: Vec::new(),
+        };
+        let provider = Provider::<Http>::try_from(config.chain.rpc_url.as_str()).unwrap();
+
+        AppState {
+            eth_client: Arc::new(SignerMiddleware::new(
+                provider,
+                AppSigner::ReadOnly { chain_id: 31337 },
+            )),
+            authenticity_contract: config.chain.authenticity_contract,
+            ownership_contract: config.chain.ownership_contract,
+            chain_id: 31337,
+            config: Arc::new(config),
+            events: None,
+            manufacturers: Arc::new(ManufacturerCache::new(Duration::from_secs(60))),
+            keys: Arc::new(keys),
+        }
+    }
+
+    // The indexed events, for the endpoints that read them
+    pub fn event_store(&self) -> Result<&EventStore, ApiError> {
+        self.events.as_deref().ok_or_else(|| {
+            ApiError::NotFound("the event indexer is disabled (INDEXER_ENABLED=false)".to_string())
+        })
+    }
+
+    // The domain the Authenticity contract hashes certificates under
+    pub fn eip712_domain(&self) -> EIP712Domain {
+        certificate_domain(
+            &self.config.signing.domain,
+            &self.config.signing.version,
+            self.chain_id,
+            self.authenticity_contract,
+        )
+    }
+}
@@ -1,3 +1,4 @@
+pub(crate) mod app_config;
 pub mod swagger_config;
 pub(crate) mod app_router;
 pub(crate) mod app_state;
@@ -3,8 +3,10 @@ use axum::Router;
 use anyhow::Result;
 use dotenv::dotenv;
 use crate::models::router_path::RouterPath;
+use crate::config::app_config::AppConfig;
 use crate::config::app_router::paths;
 use crate::config::app_state::{AppState};
+use crate::indexer::Indexer;
 
 pub async fn server() -> Result<()> {
     eprintln!("PROJECT STARTING...");
@@ -12,15 +14,22 @@ pub async fn server() -> Result<()> {
     dotenv().ok();
     // dotenv::from_path("../.env").ok();
 
-    let state = AppState::init_app_state().await?; //init_app_state().await?;
+    let config = AppConfig::load()?;
+    let bind_address = config.server.bind_address();
+
+    let state = AppState::init_app_state(config).await?;
+
+    // follow contract events in the background for the history endpoints
+    if state.config.indexer.enabled {
+        tokio::spawn(Indexer::new(state.clone())?.run());
+    }
 
     // Define routes
     let app: Router = paths(state, RouterPath::init());
 
-    eprintln!("Project started and listening on 127.0.0.1:8080");
-
     // Start the server
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:8080").await?;
+    let listener = tokio::net::TcpListener::bind(&bind_address).await?;
+    eprintln!("Project started and listening on {}", bind_address);
     axum::serve(listener, app).await?;
 
     Ok(()) // another way to say return nothing
@@ -1,25 +1,91 @@
 use crate::services::other_tests::{
     __path_generate_signature, __path_manufacturer_registers, __path_get_owner, __path_verify_signature};
-use crate::services::verify_authenticity::__path_verify_authenticity;
-use crate::services::create_eip712::__path_create_certificate;
-use crate::services::qr_code::__path_generate_qr_code;
+use crate::services::verify_authenticity::{
+    __path_manufacturer_cache_stats, __path_verify_authenticity, __path_verify_authenticity_batch,
+};
+use crate::services::create_eip712::{__path_certificate_type, __path_create_certificate};
+use crate::services::qr_code::{__path_decode_qr_code_payload, __path_generate_qr_code};
+use crate::services::verify_qr::{__path_verify_link, __path_verify_qr_image};
+use crate::services::label_sheet::__path_generate_label_sheet;
+use crate::services::issue_certificate::{
+    __path_issue_certificate, __path_issue_certificates_bulk,
+};
+use crate::services::certificate_batch::{
+    __path_issue_certificate_batch, __path_verify_batched_certificate,
+};
+use crate::services::item_history::__path_item_history;
+use crate::services::registry_snapshot::{__path_registry_snapshot, __path_verify_offline};
+use crate::services::change_ownership::{
+    __path_claim_ownership, __path_generate_ownership_code, __path_revoke_ownership_code,
+};
+use crate::services::ownership::{
+    __path_get_all_my_items, __path_get_item, __path_get_temp_owner, __path_get_user,
+    __path_is_owner, __path_user_registers, __path_verify_ownership,
+};
+use crate::error::ErrorResponse;
+use crate::models::verification_model::{
+    BatchVerdict, BatchVerificationResult, ManufacturerCacheStats, OfflineVerificationResult,
+    OfflineVerifyInput, QrVerificationResult, VerificationFailure, VerificationResult,
+};
 use utoipa::OpenApi;
-use crate::models::certificate_model::{RegInput, SignedCertificate, CertificateData, Eip712Object};
+use crate::models::certificate_model::{
+    BatchedCertificate, BulkIssueLine, BulkValidationFailed, CertificateData, CertificateType,
+    Eip712Object, IssueBatchInput, IssuedBatch, IssuedCertificate, LabelField, LabelSheetInput, LabelTemplate, PageSize, QrEcLevel, QrFormat, QrPayload, QrPayloadInput, RegInput, RowError,
+    SignedCertificate,
+};
+use crate::models::ownership_model::{
+    GenerateCodeInput, Item, ItemHashInput, ItemHistory, Owner, ProvenanceEvent, ProvenanceKind,
+    TransferRecord, TransferStatus, UserProfile, UserRegInput,
+};
 
 // Swagger/OpenAPI configuration
 #[derive(OpenApi)]
 #[openapi(
     paths(
         verify_authenticity,
+        verify_authenticity_batch,
+        verify_qr_image,
+        verify_link,
+        manufacturer_cache_stats,
         generate_signature,
         manufacturer_registers,
         get_owner,
         verify_signature,
         create_certificate,
-        generate_qr_code
+        certificate_type,
+        generate_qr_code,
+        decode_qr_code_payload,
+        generate_label_sheet,
+        issue_certificate,
+        issue_certificates_bulk,
+        issue_certificate_batch,
+        verify_batched_certificate,
+        user_registers,
+        get_user,
+        get_all_my_items,
+        get_item,
+        verify_ownership,
+        is_owner,
+        get_temp_owner,
+        generate_ownership_code,
+        claim_ownership,
+        revoke_ownership_code,
+        item_history,
+        registry_snapshot,
+        verify_offline
     ),
     components(
-        schemas(RegInput, CertificateData, SignedCertificate, Eip712Object),
+        schemas(
+            RegInput, CertificateData, SignedCertificate, Eip712Object, CertificateType,
+            IssuedCertificate, QrFormat, QrPayload, QrEcLevel, QrPayloadInput, LabelSheetInput, LabelTemplate, LabelField, PageSize, BulkIssueLine, RowError, BulkValidationFailed,
+            IssueBatchInput, BatchedCertificate, IssuedBatch,
+            UserRegInput, UserProfile, Item, Owner,
+            GenerateCodeInput, ItemHashInput, TransferRecord, TransferStatus,
+            ItemHistory, ProvenanceEvent, ProvenanceKind,
+            ErrorResponse, VerificationResult, VerificationFailure,
+            OfflineVerifyInput, OfflineVerificationResult, QrVerificationResult, BatchVerdict, BatchVerificationResult,
+            ManufacturerCacheStats
+        ),
         // responses(Item)
     ),
     tags(
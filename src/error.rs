@@ -0,0 +1,203 @@
+use crate::config::app_router::EriErrorsErrors;
+use axum::Json;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use ethers::contract::{ContractError, ContractRevert};
+use ethers::providers::{Middleware, ProviderError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use utoipa::ToSchema;
+
+// JSON body returned for every failed request
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct ErrorResponse {
+    /// Machine readable code, e.g. `DOES_NOT_EXIST` or `RPC_UNAVAILABLE`
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request itself is malformed
+    BadRequest(String),
+    /// Nothing is known about the requested resource
+    NotFound(String),
+    /// The server won't do this, e.g. sign for a manufacturer it has no key for
+    Forbidden(String),
+    /// The contract reverted with one of the custom errors in EriErrors.sol
+    Contract(EriErrorsErrors),
+    /// The contract reverted with data we could not decode
+    Reverted(String),
+    /// The node could not be reached or answered with a non-revert error
+    Rpc(String),
+    Internal(String),
+}
+
+impl ApiError {
+    pub fn bad_request(message: impl fmt::Display) -> Self {
+        ApiError::BadRequest(message.to_string())
+    }
+
+    pub fn internal(message: impl fmt::Display) -> Self {
+        ApiError::Internal(message.to_string())
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::Contract(error) => contract_error_status(error),
+            ApiError::Reverted(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Rpc(_) => StatusCode::BAD_GATEWAY,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> String {
+        match self {
+            ApiError::BadRequest(_) => "BAD_REQUEST".to_string(),
+            ApiError::NotFound(_) => "NOT_FOUND".to_string(),
+            ApiError::Forbidden(_) => "FORBIDDEN".to_string(),
+            ApiError::Contract(error) => contract_error_name(error).to_string(),
+            ApiError::Reverted(_) => "REVERTED".to_string(),
+            ApiError::Rpc(_) => "RPC_UNAVAILABLE".to_string(),
+            ApiError::Internal(_) => "INTERNAL_ERROR".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::BadRequest(message)
+            | ApiError::NotFound(message)
+            | ApiError::Forbidden(message)
+            | ApiError::Reverted(message)
+            | ApiError::Rpc(message)
+            | ApiError::Internal(message) => write!(f, "{}", message),
+            ApiError::Contract(error) => {
+                write!(
+                    f,
+                    "contract reverted with {}({})",
+                    contract_error_name(error),
+                    error
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        eprintln!("Request failed: {}", self);
+        let body = ErrorResponse::from(&self);
+        (self.status(), Json(body)).into_response()
+    }
+}
+
+impl From<&ApiError> for ErrorResponse {
+    fn from(error: &ApiError) -> Self {
+        ErrorResponse {
+            code: error.code(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl<M: Middleware> From<ContractError<M>> for ApiError {
+    fn from(error: ContractError<M>) -> Self {
+        match error.as_revert() {
+            Some(data) => match EriErrorsErrors::decode_with_selector(data) {
+                Some(EriErrorsErrors::RevertString(reason)) => ApiError::Reverted(reason),
+                Some(decoded) => ApiError::Contract(decoded),
+                None => ApiError::Reverted(format!("execution reverted: {}", data)),
+            },
+            None if error.is_middleware_error() || error.is_provider_error() => {
+                ApiError::Rpc(error.to_string())
+            }
+            None => ApiError::Internal(error.to_string()),
+        }
+    }
+}
+
+// store errors are our own fault, not the client's
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        ApiError::Internal(format!("{:#}", error))
+    }
+}
+
+impl From<ProviderError> for ApiError {
+    fn from(error: ProviderError) -> Self {
+        ApiError::Rpc(error.to_string())
+    }
+}
+
+fn contract_error_name(error: &EriErrorsErrors) -> &'static str {
+    match error {
+        EriErrorsErrors::ONLY_OWNER(_) => "ONLY_OWNER",
+        EriErrorsErrors::ALREADY_REGISTERED(_) => "ALREADY_REGISTERED",
+        EriErrorsErrors::ADDRESS_ZERO(_) => "ADDRESS_ZERO",
+        EriErrorsErrors::CODE_ALREADY_GENERATED(_) => "CODE_ALREADY_GENERATED",
+        EriErrorsErrors::UNAUTHORIZED(_) => "UNAUTHORIZED",
+        EriErrorsErrors::ITEM_DOESNT_EXIST(_) => "ITEM_DOESNT_EXIST",
+        EriErrorsErrors::DOES_NOT_EXIST(_) => "DOES_NOT_EXIST",
+        EriErrorsErrors::CONTRACT_DOEST_NOT_EXIST(_) => "CONTRACT_DOEST_NOT_EXIST",
+        EriErrorsErrors::NAME_ALREADY_EXIST(_) => "NAME_ALREADY_EXIST",
+        EriErrorsErrors::INVALID_SIGNATURE(_) => "INVALID_SIGNATURE",
+        EriErrorsErrors::ITEM_CLAIMED_ALREADY(_) => "ITEM_CLAIMED_ALREADY",
+        EriErrorsErrors::ITEM_NOT_CLAIMED_YET(_) => "ITEM_NOT_CLAIMED_YET",
+        EriErrorsErrors::NOT_REGISTERED(_) => "NOT_REGISTERED",
+        EriErrorsErrors::NAME_NOT_AVAILABLE(_) => "NAME_NOT_AVAILABLE",
+        EriErrorsErrors::USER_DOES_NOT_EXIST(_) => "USER_DOES_NOT_EXIST",
+        EriErrorsErrors::CANNOT_GENERATE_CODE_FOR_YOURSELF(_) => {
+            "CANNOT_GENERATE_CODE_FOR_YOURSELF"
+        }
+        EriErrorsErrors::USERNAME_MUST_BE_AT_LEAST_3_LETTERS(_) => {
+            "USERNAME_MUST_BE_AT_LEAST_3_LETTERS"
+        }
+        EriErrorsErrors::INVALID_MANUFACTURER_NAME(_) => "INVALID_MANUFACTURER_NAME",
+        EriErrorsErrors::AUTHENTICITY_NOT_SET(_) => "AUTHENTICITY_NOT_SET",
+        EriErrorsErrors::ECDSAInvalidSignature(_) => "ECDSA_INVALID_SIGNATURE",
+        EriErrorsErrors::ECDSAInvalidSignatureLength(_) => "ECDSA_INVALID_SIGNATURE_LENGTH",
+        EriErrorsErrors::ECDSAInvalidSignatureS(_) => "ECDSA_INVALID_SIGNATURE_S",
+        EriErrorsErrors::RevertString(_) => "REVERTED",
+    }
+}
+
+fn contract_error_status(error: &EriErrorsErrors) -> StatusCode {
+    match error {
+        EriErrorsErrors::ITEM_DOESNT_EXIST(_)
+        | EriErrorsErrors::DOES_NOT_EXIST(_)
+        | EriErrorsErrors::CONTRACT_DOEST_NOT_EXIST(_)
+        | EriErrorsErrors::USER_DOES_NOT_EXIST(_) => StatusCode::NOT_FOUND,
+
+        EriErrorsErrors::ALREADY_REGISTERED(_)
+        | EriErrorsErrors::CODE_ALREADY_GENERATED(_)
+        | EriErrorsErrors::NAME_ALREADY_EXIST(_)
+        | EriErrorsErrors::NAME_NOT_AVAILABLE(_)
+        | EriErrorsErrors::ITEM_CLAIMED_ALREADY(_)
+        | EriErrorsErrors::ITEM_NOT_CLAIMED_YET(_) => StatusCode::CONFLICT,
+
+        EriErrorsErrors::ONLY_OWNER(_)
+        | EriErrorsErrors::UNAUTHORIZED(_)
+        | EriErrorsErrors::NOT_REGISTERED(_) => StatusCode::FORBIDDEN,
+
+        EriErrorsErrors::INVALID_SIGNATURE(_)
+        | EriErrorsErrors::ECDSAInvalidSignature(_)
+        | EriErrorsErrors::ECDSAInvalidSignatureLength(_)
+        | EriErrorsErrors::ECDSAInvalidSignatureS(_)
+        | EriErrorsErrors::RevertString(_) => StatusCode::UNPROCESSABLE_ENTITY,
+
+        EriErrorsErrors::ADDRESS_ZERO(_)
+        | EriErrorsErrors::CANNOT_GENERATE_CODE_FOR_YOURSELF(_)
+        | EriErrorsErrors::USERNAME_MUST_BE_AT_LEAST_3_LETTERS(_)
+        | EriErrorsErrors::INVALID_MANUFACTURER_NAME(_) => StatusCode::BAD_REQUEST,
+
+        // the contracts are deployed but Ownership was never linked to Authenticity
+        EriErrorsErrors::AUTHENTICITY_NOT_SET(_) => StatusCode::SERVICE_UNAVAILABLE,
+    }
+}
@@ -0,0 +1,244 @@
+pub(crate) mod store;
+
+use crate::config::app_state::AppState;
+use crate::models::events::{
+    AuthenticitySet, CodeRevoked, ItemCreated, ManufacturerRegistered, OwnershipClaimed,
+    OwnershipCode,
+};
+use anyhow::{Context, anyhow};
+use ethers::contract::EthEvent;
+use ethers::prelude::*;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use store::{BlockRecord, Checkpoint, EventStore, REORG_DEPTH, StoredEvent};
+
+// topic0 of every event the indexer follows, with the name it is stored under
+fn followed_events() -> Vec<(H256, &'static str)> {
+    vec![
+        (
+            ManufacturerRegistered::signature(),
+            "ManufacturerRegistered",
+        ),
+        (ItemCreated::signature(), "ItemCreated"),
+        (OwnershipCode::signature(), "OwnershipCode"),
+        (OwnershipClaimed::signature(), "OwnershipClaimed"),
+        (CodeRevoked::signature(), "CodeRevoked"),
+        (AuthenticitySet::signature(), "AuthenticitySet"),
+    ]
+}
+
+// Follows the Authenticity and Ownership logs into the event store
+pub struct Indexer {
+    state: AppState,
+    store: Arc<EventStore>,
+    events: Vec<(H256, &'static str)>,
+}
+
+impl Indexer {
+    pub fn new(state: AppState) -> anyhow::Result<Indexer> {
+        // a store built for another chain or other deployments is useless, start over
+        let scope = format!(
+            "{}:{:?}:{:?}",
+            state.chain_id, state.authenticity_contract, state.ownership_contract
+        );
+        let store = state
+            .events
+            .clone()
+            .context("the event store is only opened when the indexer is enabled")?;
+        store.ensure_scope(&scope)?;
+
+        Ok(Indexer {
+            state,
+            store,
+            events: followed_events(),
+        })
+    }
+
+    // Runs forever; RPC errors are logged and retried on the next poll
+    pub async fn run(self) {
+        let poll_interval = Duration::from_secs(self.state.config.indexer.poll_interval_secs);
+        eprintln!("🔎 Event indexer started");
+
+        loop {
+            match self.sync_once().await {
+                Ok(true) => tokio::time::sleep(poll_interval).await,
+                Ok(false) => {}
+                Err(e) => {
+                    eprintln!("Indexer error: {:#}", e);
+                    tokio::time::sleep(poll_interval).await;
+                }
+            }
+        }
+    }
+
+    async fn block(&self, number: u64) -> anyhow::Result<BlockRecord> {
+        let block = self
+            .state
+            .eth_client
+            .get_block(number)
+            .await?
+            .ok_or_else(|| anyhow!("block {} not found", number))?;
+
+        Ok(BlockRecord {
+            number,
+            hash: block
+                .hash
+                .ok_or_else(|| anyhow!("block {} is still pending", number))?,
+            timestamp: block.timestamp.low_u64(),
+        })
+    }
+
+    // Processes one batch of blocks, returns true once the head is reached
+    async fn sync_once(&self) -> anyhow::Result<bool> {
+        let store = &self.store;
+        let config = &self.state.config.indexer;
+        let head = self.state.eth_client.get_block_number().await?.as_u64();
+
+        let from = match store.checkpoint()? {
+            Some(checkpoint) => {
+                if !self.is_canonical(&checkpoint).await? {
+                    self.handle_reorg(checkpoint).await?;
+                    return Ok(false);
+                }
+                checkpoint.number + 1
+            }
+            None => config.start_block,
+        };
+
+        if from > head {
+            return Ok(true);
+        }
+        let to = head.min(from + config.batch_size - 1);
+        // taken before the logs, so a block reorged in between is caught below
+        let last = self.block(to).await?;
+
+        let filter = Filter::new()
+            .address(vec![
+                self.state.authenticity_contract,
+                self.state.ownership_contract,
+            ])
+            .topic0(
+                self.events
+                    .iter()
+                    .map(|(topic, _)| *topic)
+                    .collect::<Vec<_>>(),
+            )
+            .from_block(from)
+            .to_block(to);
+        let logs = self
+            .state
+            .eth_client
+            .get_logs(&filter)
+            .await
+            .with_context(|| format!("eth_getLogs failed for blocks {}..={}", from, to))?;
+
+        // headers give the timestamps, and a hash to check each log against
+        let mut blocks = BTreeMap::new();
+        for log in &logs {
+            let number = log.block_number.map(|n| n.as_u64()).unwrap_or_default();
+            if let std::collections::btree_map::Entry::Vacant(entry) = blocks.entry(number) {
+                entry.insert(self.block(number).await?);
+            }
+        }
+
+        // getLogs covers blocks without logs too, if `to` is unchanged the whole range is
+        // the chain the logs came from. Otherwise a replaced empty block could hide logs
+        let checkpoint = Checkpoint {
+            number: last.number,
+            hash: last.hash,
+        };
+        if !self.is_canonical(&checkpoint).await? {
+            eprintln!("Block {} changed while indexing, retrying", to);
+            return Ok(false);
+        }
+
+        let mut events = Vec::with_capacity(logs.len());
+        for log in logs {
+            let number = log.block_number.map(|n| n.as_u64()).unwrap_or_default();
+            let block = blocks[&number];
+
+            // the chain moved under us between getLogs and getBlock, retry the range
+            if log.block_hash != Some(block.hash) || log.removed == Some(true) {
+                eprintln!("Block {} changed while indexing, retrying", number);
+                return Ok(false);
+            }
+
+            let Some((_, name)) = self
+                .events
+                .iter()
+                .find(|(topic, _)| log.topics.first() == Some(topic))
+            else {
+                continue;
+            };
+
+            events.push(StoredEvent {
+                block_number: number,
+                timestamp: block.timestamp,
+                tx_hash: log.transaction_hash.unwrap_or_default(),
+                log_index: log.log_index.map(|i| i.low_u64()).unwrap_or_default(),
+                contract: log.address,
+                name: name.to_string(),
+                topics: log.topics[1..].to_vec(),
+                data: log.data.to_vec(),
+            });
+        }
+
+        let blocks: Vec<BlockRecord> = blocks.into_values().collect();
+        store.commit_range(&blocks, &events, &last)?;
+
+        // a cached "not registered" answer is wrong from here on
+        for registered in events
+            .iter()
+            .filter(|event| event.name == "ManufacturerRegistered")
+            .filter_map(|event| event.decode::<ManufacturerRegistered>())
+        {
+            self.state
+                .manufacturers
+                .invalidate(registered.manufacturer_address);
+        }
+
+        if !events.is_empty() {
+            println!(
+                "📚 Indexed {} events from blocks {}..={}",
+                events.len(),
+                from,
+                to
+            );
+        }
+
+        Ok(to == head)
+    }
+
+    async fn is_canonical(&self, block: &Checkpoint) -> anyhow::Result<bool> {
+        let canonical = self.state.eth_client.get_block(block.number).await?;
+        Ok(canonical.and_then(|b| b.hash) == Some(block.hash))
+    }
+
+    // Walks back through the stored hashes to the newest block still on the chain
+    async fn handle_reorg(&self, checkpoint: Checkpoint) -> anyhow::Result<()> {
+        let store = &self.store;
+        eprintln!(
+            "⚠️ Reorg detected at block {}, rewinding",
+            checkpoint.number
+        );
+        self.state.manufacturers.clear();
+
+        for block in store.blocks_before(checkpoint.number, REORG_DEPTH)? {
+            let checkpoint = Checkpoint {
+                number: block.number,
+                hash: block.hash,
+            };
+            if self.is_canonical(&checkpoint).await? {
+                eprintln!("    Rewound to block {}", block.number);
+                return store.rewind(Some(&block));
+            }
+        }
+
+        eprintln!(
+            "    No common ancestor within {} blocks, reindexing",
+            REORG_DEPTH
+        );
+        store.rewind(None)
+    }
+}
@@ -0,0 +1,494 @@
+use anyhow::Context;
+use ethers::abi::RawLog;
+use ethers::contract::EthEvent;
+use ethers::types::{Address, H256};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+// block hashes older than this (and without events) are dropped, deeper reorgs reindex from scratch
+pub const REORG_DEPTH: u64 = 256;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS blocks (
+    number INTEGER PRIMARY KEY,
+    hash TEXT NOT NULL,
+    timestamp INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS events (
+    block_number INTEGER NOT NULL,
+    tx_hash TEXT NOT NULL,
+    log_index INTEGER NOT NULL,
+    contract TEXT NOT NULL,
+    name TEXT NOT NULL,
+    topic1 TEXT,
+    topic2 TEXT,
+    topic3 TEXT,
+    data BLOB NOT NULL,
+    PRIMARY KEY (tx_hash, log_index)
+);
+CREATE INDEX IF NOT EXISTS events_by_topic1 ON events (name, topic1);
+CREATE INDEX IF NOT EXISTS events_by_topic2 ON events (name, topic2);
+CREATE INDEX IF NOT EXISTS events_by_block ON events (block_number);
+";
+
+// A contract log as it was persisted, topics[0] is replaced by the event name
+#[derive(Clone, Debug)]
+pub struct StoredEvent {
+    pub block_number: u64,
+    pub timestamp: u64,
+    pub tx_hash: H256,
+    pub log_index: u64,
+    pub contract: Address,
+    pub name: String,
+    pub topics: Vec<H256>,
+    pub data: Vec<u8>,
+}
+
+impl StoredEvent {
+    pub fn decode<E: EthEvent>(&self) -> Option<E> {
+        let mut topics = vec![E::signature()];
+        topics.extend(self.topics.iter().copied());
+        E::decode_log(&RawLog {
+            topics,
+            data: self.data.clone(),
+        })
+        .ok()
+    }
+
+    // (block, log index) orders events across the whole chain
+    pub fn position(&self) -> (u64, u64) {
+        (self.block_number, self.log_index)
+    }
+}
+
+// Last block the indexer fully processed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Checkpoint {
+    pub number: u64,
+    pub hash: H256,
+}
+
+// Header fields kept per block, for reorg checks and event timestamps
+#[derive(Clone, Copy, Debug)]
+pub struct BlockRecord {
+    pub number: u64,
+    pub hash: H256,
+    pub timestamp: u64,
+}
+
+// Embedded SQLite store the indexer writes to and history queries read from
+pub struct EventStore {
+    conn: Mutex<Connection>,
+}
+
+fn hex_of<T: std::fmt::Debug>(value: &T) -> String {
+    format!("{:?}", value)
+}
+
+fn parse_hex<T: std::str::FromStr>(value: String) -> rusqlite::Result<T> {
+    value.parse().map_err(|_| {
+        rusqlite::Error::FromSqlConversionFailure(
+            0,
+            rusqlite::types::Type::Text,
+            format!("invalid hex value {:?}", value).into(),
+        )
+    })
+}
+
+impl EventStore {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<EventStore> {
+        let path = path.as_ref();
+        let conn = Connection::open(path)
+            .with_context(|| format!("failed to open event database {}", path.display()))?;
+        conn.execute_batch(SCHEMA)
+            .context("failed to create the event database schema")?;
+
+        Ok(EventStore {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        // a panic while holding the lock can't leave a half-written transaction behind
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Wipes the store when it was built for another chain or other contracts
+    pub fn ensure_scope(&self, scope: &str) -> anyhow::Result<()> {
+        let mut conn = self.conn();
+        let current: Option<String> = conn
+            .query_row("SELECT value FROM meta WHERE key = 'scope'", [], |row| {
+                row.get(0)
+            })
+            .optional()?;
+
+        if current.as_deref() == Some(scope) {
+            return Ok(());
+        }
+        if current.is_some() {
+            eprintln!("Event database was built for {:?}, reindexing", current);
+        }
+
+        let tx = conn.transaction()?;
+        tx.execute_batch("DELETE FROM events; DELETE FROM blocks; DELETE FROM meta;")?;
+        tx.execute(
+            "INSERT INTO meta (key, value) VALUES ('scope', ?1)",
+            params![scope],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn checkpoint(&self) -> anyhow::Result<Option<Checkpoint>> {
+        let conn = self.conn();
+        let checkpoint = conn
+            .query_row(
+                "SELECT b.number, b.hash FROM meta m JOIN blocks b ON b.number = CAST(m.value AS INTEGER)
+                 WHERE m.key = 'checkpoint'",
+                [],
+                |row| {
+                    Ok(Checkpoint {
+                        number: row.get::<_, i64>(0)? as u64,
+                        hash: parse_hex(row.get(1)?)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(checkpoint)
+    }
+
+    // Every stored `name` event whose indexed argument at `topic` (1-based) equals `value`, oldest first
+    pub fn find_events(
+        &self,
+        name: &str,
+        topic: usize,
+        value: H256,
+    ) -> anyhow::Result<Vec<StoredEvent>> {
+        let column = match topic {
+            1 => "topic1",
+            2 => "topic2",
+            3 => "topic3",
+            _ => anyhow::bail!("events have at most 3 indexed topics, got {}", topic),
+        };
+
+        self.query_events(
+            &format!("e.name = ?1 AND e.{} = ?2", column),
+            params![name, hex_of(&value)],
+        )
+    }
+
+    // Every stored event of one kind, oldest first
+    pub fn events_named(&self, name: &str) -> anyhow::Result<Vec<StoredEvent>> {
+        self.query_events("e.name = ?1", params![name])
+    }
+
+    fn query_events(
+        &self,
+        filter: &str,
+        params: &[&dyn rusqlite::ToSql],
+    ) -> anyhow::Result<Vec<StoredEvent>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT e.block_number, b.timestamp, e.tx_hash, e.log_index, e.contract, e.name,
+                    e.topic1, e.topic2, e.topic3, e.data
+             FROM events e JOIN blocks b ON b.number = e.block_number
+             WHERE {}
+             ORDER BY e.block_number, e.log_index",
+            filter
+        ))?;
+        let events = stmt
+            .query_map(params, |row| {
+                let topics = [row.get::<_, Option<String>>(6)?, row.get(7)?, row.get(8)?]
+                    .into_iter()
+                    .flatten()
+                    .map(parse_hex)
+                    .collect::<rusqlite::Result<Vec<H256>>>()?;
+
+                Ok(StoredEvent {
+                    block_number: row.get::<_, i64>(0)? as u64,
+                    timestamp: row.get::<_, i64>(1)? as u64,
+                    tx_hash: parse_hex(row.get(2)?)?,
+                    log_index: row.get::<_, i64>(3)? as u64,
+                    contract: parse_hex(row.get(4)?)?,
+                    name: row.get(5)?,
+                    topics,
+                    data: row.get(9)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(events)
+    }
+
+    // Known block hashes below `number`, newest first, to search for a common ancestor
+    pub fn blocks_before(&self, number: u64, limit: u64) -> anyhow::Result<Vec<BlockRecord>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT number, hash, timestamp FROM blocks WHERE number < ?1 ORDER BY number DESC LIMIT ?2",
+        )?;
+        let blocks = stmt
+            .query_map(params![number as i64, limit as i64], |row| {
+                Ok(BlockRecord {
+                    number: row.get::<_, i64>(0)? as u64,
+                    hash: parse_hex(row.get(1)?)?,
+                    timestamp: row.get::<_, i64>(2)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(blocks)
+    }
+
+    // Persists one processed block range atomically and moves the checkpoint to `last`
+    pub fn commit_range(
+        &self,
+        blocks: &[BlockRecord],
+        events: &[StoredEvent],
+        last: &BlockRecord,
+    ) -> anyhow::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+
+        for block in blocks.iter().chain(std::iter::once(last)) {
+            tx.execute(
+                "INSERT OR REPLACE INTO blocks (number, hash, timestamp) VALUES (?1, ?2, ?3)",
+                params![
+                    block.number as i64,
+                    hex_of(&block.hash),
+                    block.timestamp as i64
+                ],
+            )?;
+        }
+
+        for event in events {
+            tx.execute(
+                "INSERT OR REPLACE INTO events
+                 (block_number, tx_hash, log_index, contract, name, topic1, topic2, topic3, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    event.block_number as i64,
+                    hex_of(&event.tx_hash),
+                    event.log_index as i64,
+                    hex_of(&event.contract),
+                    event.name,
+                    event.topics.first().map(hex_of),
+                    event.topics.get(1).map(hex_of),
+                    event.topics.get(2).map(hex_of),
+                    event.data,
+                ],
+            )?;
+        }
+
+        tx.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES ('checkpoint', ?1)",
+            params![last.number.to_string()],
+        )?;
+
+        // range ends are only needed for reorg checks near the head
+        tx.execute(
+            "DELETE FROM blocks WHERE number < ?1
+             AND number NOT IN (SELECT block_number FROM events)",
+            params![last.number.saturating_sub(REORG_DEPTH) as i64],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Drops everything above `ancestor`, or the whole index when there is no common ancestor
+    pub fn rewind(&self, ancestor: Option<&BlockRecord>) -> anyhow::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+
+        match ancestor {
+            Some(block) => {
+                tx.execute(
+                    "DELETE FROM events WHERE block_number > ?1",
+                    params![block.number as i64],
+                )?;
+                tx.execute(
+                    "DELETE FROM blocks WHERE number > ?1",
+                    params![block.number as i64],
+                )?;
+                tx.execute(
+                    "INSERT OR REPLACE INTO meta (key, value) VALUES ('checkpoint', ?1)",
+                    params![block.number.to_string()],
+                )?;
+            }
+            None => {
+                tx.execute_batch(
+                    "DELETE FROM events; DELETE FROM blocks; DELETE FROM meta WHERE key = 'checkpoint';",
+                )?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> EventStore {
+        EventStore::open(":memory:").unwrap()
+    }
+
+    fn block(number: u64, fork: u8) -> BlockRecord {
+        BlockRecord {
+            number,
+            hash: H256::from_low_u64_be(number * 1000 + u64::from(fork)),
+            timestamp: 1_700_000_000 + number * 12,
+        }
+    }
+
+    fn event(block: &BlockRecord, log_index: u64) -> StoredEvent {
+        StoredEvent {
+            block_number: block.number,
+            timestamp: block.timestamp,
+            tx_hash: H256::from_low_u64_be(block.number),
+            log_index,
+            contract: Address::repeat_byte(0x22),
+            name: "OwnershipClaimed".to_string(),
+            topics: vec![H256::repeat_byte(0xaa), H256::from_low_u64_be(block.number)],
+            data: vec![1, 2, 3],
+        }
+    }
+
+    fn all_blocks(store: &EventStore) -> Vec<u64> {
+        let mut numbers: Vec<u64> = store
+            .blocks_before(i64::MAX as u64, 1000)
+            .unwrap()
+            .iter()
+            .map(|block| block.number)
+            .collect();
+        numbers.reverse();
+        numbers
+    }
+
+    fn event_blocks(store: &EventStore) -> Vec<u64> {
+        store
+            .events_named("OwnershipClaimed")
+            .unwrap()
+            .iter()
+            .map(|event| event.block_number)
+            .collect()
+    }
+
+    // blocks 1 to 3 then 4 to 6, with events in 2, 3 and 5
+    fn two_ranges(store: &EventStore) {
+        store
+            .commit_range(
+                &[block(2, 0)],
+                &[event(&block(2, 0), 0), event(&block(3, 0), 1)],
+                &block(3, 0),
+            )
+            .unwrap();
+        store
+            .commit_range(&[block(5, 0)], &[event(&block(5, 0), 0)], &block(6, 0))
+            .unwrap();
+    }
+
+    #[test]
+    fn commit_range_moves_the_checkpoint() {
+        let store = store();
+        assert_eq!(store.checkpoint().unwrap(), None);
+
+        two_ranges(&store);
+
+        assert_eq!(
+            store.checkpoint().unwrap(),
+            Some(Checkpoint {
+                number: 6,
+                hash: block(6, 0).hash
+            })
+        );
+        assert_eq!(all_blocks(&store), vec![2, 3, 5, 6]);
+        assert_eq!(event_blocks(&store), vec![2, 3, 5]);
+
+        let stored = &store.events_named("OwnershipClaimed").unwrap()[0];
+        assert_eq!(stored.timestamp, block(2, 0).timestamp);
+        assert_eq!(stored.topics, event(&block(2, 0), 0).topics);
+        assert_eq!(stored.data, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn rewind_drops_everything_above_the_fork() {
+        let store = store();
+        two_ranges(&store);
+
+        let ancestor = store.blocks_before(5, 1).unwrap()[0];
+        assert_eq!(ancestor.number, 3);
+        store.rewind(Some(&ancestor)).unwrap();
+
+        assert_eq!(
+            store.checkpoint().unwrap(),
+            Some(Checkpoint {
+                number: 3,
+                hash: block(3, 0).hash
+            })
+        );
+        assert_eq!(all_blocks(&store), vec![2, 3]);
+        assert_eq!(event_blocks(&store), vec![2, 3]);
+
+        // the new fork's blocks are indexed over the old ones
+        store
+            .commit_range(&[block(4, 1)], &[event(&block(4, 1), 0)], &block(5, 1))
+            .unwrap();
+        assert_eq!(store.checkpoint().unwrap().unwrap().hash, block(5, 1).hash);
+        assert_eq!(event_blocks(&store), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn rewind_without_ancestor_clears_the_index() {
+        let store = store();
+        two_ranges(&store);
+
+        store.rewind(None).unwrap();
+
+        assert_eq!(store.checkpoint().unwrap(), None);
+        assert!(all_blocks(&store).is_empty());
+        assert!(event_blocks(&store).is_empty());
+    }
+
+    #[test]
+    fn commit_range_is_idempotent() {
+        let store = store();
+        two_ranges(&store);
+        two_ranges(&store);
+
+        assert_eq!(store.checkpoint().unwrap().unwrap().number, 6);
+        assert_eq!(all_blocks(&store), vec![2, 3, 5, 6]);
+        assert_eq!(event_blocks(&store), vec![2, 3, 5]);
+    }
+
+    #[test]
+    fn old_blocks_without_events_are_pruned() {
+        let store = store();
+        two_ranges(&store);
+
+        let head = block(6 + REORG_DEPTH + 10, 0);
+        store.commit_range(&[], &[], &head).unwrap();
+
+        // 6 has no events and is past the reorg window, 2, 3 and 5 keep their timestamps
+        assert_eq!(all_blocks(&store), vec![2, 3, 5, head.number]);
+    }
+
+    #[test]
+    fn new_scope_wipes_the_store() {
+        let store = store();
+        store.ensure_scope("31337:a").unwrap();
+        two_ranges(&store);
+
+        store.ensure_scope("31337:a").unwrap();
+        assert_eq!(event_blocks(&store), vec![2, 3, 5]);
+
+        store.ensure_scope("1:b").unwrap();
+        assert_eq!(store.checkpoint().unwrap(), None);
+        assert!(event_blocks(&store).is_empty());
+    }
+}
@@ -0,0 +1,12 @@
+pub mod cli;
+
+mod cache;
+mod config;
+mod error;
+mod indexer;
+mod models;
+mod services;
+mod signer;
+mod utility;
+
+pub use config::server::server;
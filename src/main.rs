@@ -1,11 +1,7 @@
-use config::server::server;
-
-mod config;
-mod models;
-mod services;
-mod utility;
+use eri_server::server;
 
 #[tokio::main]
-async fn main() {
-    server().await.expect("Error!");
+async fn main() -> anyhow::Result<()> {
+    // errors (e.g. a bad config) are printed with their context instead of panicking
+    server().await
 }
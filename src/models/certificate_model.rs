@@ -1,107 +1,15 @@
 use crate::config::app_router::authenticity;
-use crate::utility::to_meta_hash;
-use ethabi::ethereum_types::{Address, U256};
-use ethers::contract::EthEvent;
-use ethers::types::transaction::eip712::{EIP712Domain, Eip712, Eip712Error};
-use ethers::utils::keccak256;
+use crate::error::ErrorResponse;
+use eri_core::{SignedCertificateBatch, TypedStruct};
+use ethabi::ethereum_types::{Address, H256};
+use ethers::types::transaction::eip712::EIP712Domain;
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
-use std::env;
-use ethabi::Bytes;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 use validator::{Validate, ValidationError};
 
-// Certificate struct for EIP-712
-#[derive(Clone, Serialize, Deserialize, Debug)]
-pub struct Certificate {
-    pub name: String,
-    pub unique_id: String,
-    pub serial: String,
-    pub date: U256,
-    pub owner: Address,
-    pub metadata_hash: [u8; 32],
-    pub metadata: Vec<String>,
-}
-
-// EIP-712 implementation
-impl Eip712 for Certificate {
-    type Error = Eip712Error;
-
-    fn domain_separator(&self) -> Result<[u8; 32], Self::Error> {
-        let domain = self.domain()?;
-        let type_hash = keccak256(
-            "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)",
-        );
-
-        let name_hash = keccak256(domain.name.unwrap_or_default().as_bytes());
-        let version_hash = keccak256(domain.version.unwrap_or_default().as_bytes());
-        let chain_id = U256::from(domain.chain_id.unwrap_or_default());
-        let verifying_contract = domain.verifying_contract.unwrap_or_default();
-
-        let encoded = ethers::abi::encode(&[
-            ethers::abi::Token::FixedBytes(type_hash.to_vec()),
-            ethers::abi::Token::FixedBytes(name_hash.to_vec()),
-            ethers::abi::Token::FixedBytes(version_hash.to_vec()),
-            ethers::abi::Token::Uint(chain_id),
-            ethers::abi::Token::Address(verifying_contract),
-        ]);
-        Ok(keccak256(&encoded))
-    }
-    fn domain(&self) -> Result<EIP712Domain, Self::Error> {
-        let factory_address: Address = env::var("CONTRACT_ADDRESS")
-            .expect("CONTRACT ADDRESS NOT SET")
-            .parse()
-            .expect("Invalid contract address");
-
-        let chain_id = env::var("CHAIN_ID").unwrap().parse::<usize>().unwrap();
-
-        Ok(EIP712Domain {
-            // name: Some("CertificateAuth".to_string()),
-            name: Some(env::var("SIGNING_DOMAIN").unwrap()),
-            // version: Some("1".to_string()),
-            version: Some(env::var("SIGNATURE_VERSION").unwrap()),
-            chain_id: Some(U256::from(chain_id).into()),
-            verifying_contract: Some(factory_address),
-            salt: None,
-        })
-    }
-
-    fn type_hash() -> Result<[u8; 32], Self::Error> {
-        
-        // let certificate = env::var("CERTIFICATE").unwrap();
-        
-        Ok(keccak256( //i will add it to the env file
-                      env::var("CERTIFICATE").unwrap(),     //"Certificate(string name,string uniqueId,string serial,uint256 date,address owner,bytes32 metadataHash)",
-        ))
-    }
-
-    fn struct_hash(&self) -> Result<[u8; 32], Self::Error> {
-
-        let encoded = ethers::abi::encode(&[
-            ethers::abi::Token::FixedBytes(Self::type_hash()?.to_vec()),
-            ethers::abi::Token::FixedBytes(keccak256(self.name.as_bytes()).to_vec()),
-            ethers::abi::Token::FixedBytes(keccak256(self.unique_id.as_bytes()).to_vec()),
-            ethers::abi::Token::FixedBytes(keccak256(self.serial.as_bytes()).to_vec()),
-            ethers::abi::Token::Uint(self.date),
-            ethers::abi::Token::Address(self.owner),
-            ethers::abi::Token::FixedBytes(self.metadata_hash.to_vec()),
-        ]);
-        
-        Ok(keccak256(&encoded))
-    }
-
-    fn encode_eip712(&self) -> Result<[u8; 32], Self::Error> {
-        let domain_separator = self.domain_separator()?;
-        let struct_hash = self.struct_hash()?;
-
-        let mut bytes = Vec::with_capacity(2 + 32 + 32);
-        bytes.extend_from_slice(b"\x19\x01"); //this is adding the \x19Ethereum Signed Message prefix
-        bytes.extend_from_slice(&domain_separator);
-        bytes.extend_from_slice(&struct_hash);
-
-        Ok(keccak256(&bytes))
-    }
-}
+// the certificate itself lives in eri-core so other services can sign and verify without the server
+pub use eri_core::certificate::{CERTIFICATE_TYPE, Certificate};
 
 #[derive(Clone, Serialize, Deserialize, Debug, ToSchema, Validate)]
 pub struct SignedCertificate {
@@ -122,17 +30,19 @@ pub struct SignedCertificate {
     pub signature: String,
 }
 
-fn validate_address(address: &String) -> Result<(), ValidationError> {
+fn validate_address(address: &str) -> Result<(), ValidationError> {
     if !address.starts_with("0x") || address.len() != 42 || hex::decode(&address[2..]).is_err() {
         return Err(ValidationError::new("Invalid Ethereum address"));
     }
     Ok(())
 }
-fn validate_signature(signature: &String) -> Result<(), ValidationError> {
-    if !signature.starts_with("0x")
-        || (signature.len() != 130 && signature.len() != 132)
-        || hex::decode(&signature[2..]).is_err()
-    {
+// 65 byte r || s || v or 64 byte EIP-2098 compact, the rest is checked by `parse_signature`
+fn validate_signature(signature: &str) -> Result<(), ValidationError> {
+    let len = signature
+        .strip_prefix("0x")
+        .and_then(|hex| hex::decode(hex).ok())
+        .map(|bytes| bytes.len());
+    if !matches!(len, Some(64 | 65)) {
         return Err(ValidationError::new("Invalid signature"));
     }
     Ok(())
@@ -141,19 +51,12 @@ fn validate_signature(signature: &String) -> Result<(), ValidationError> {
 impl TryFrom<SignedCertificate> for Certificate {
     type Error = anyhow::Error;
     fn try_from(dto: SignedCertificate) -> Result<Self, Self::Error> {
-        
-        Ok(Certificate {
-            name: dto.name,
-            unique_id: dto.unique_id,
-            serial: dto.serial,
-            date: U256::from(dto.date),
-            owner: dto
-                .owner
-                .parse()
-                .map_err(|_| anyhow::anyhow!("Invalid address format"))?,
-            metadata_hash: to_meta_hash(&dto.metadata),
-            metadata: dto.metadata,
-        })
+        let owner = dto
+            .owner
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid address format"))?;
+
+        Ok(Certificate::new(dto.name, dto.unique_id, dto.serial, dto.date, owner, dto.metadata))
     }
 }
 
@@ -210,6 +113,32 @@ pub struct Eip712Object {
     pub value: serde_json::Value,
 }
 
+// The canonical Certificate type, for clients and deploy scripts that sign or
+// deploy without hard-coding their own copy
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct CertificateType {
+    pub primary_type: String,
+    /// the string to pass as `certificate` to the Authenticity constructor
+    pub encoded_type: String,
+    #[schema(value_type = String)]
+    pub type_hash: H256,
+    pub types: serde_json::Value,
+    /// the domain this server signs and verifies certificates under
+    pub domain: CustomEIP712Domain,
+}
+
+impl CertificateType {
+    pub fn new(domain: EIP712Domain) -> Self {
+        Self {
+            primary_type: Certificate::PRIMARY_TYPE.to_string(),
+            encoded_type: Certificate::ENCODED_TYPE.to_string(),
+            type_hash: H256::from(Certificate::hash_type()),
+            types: Certificate::types_json(),
+            domain: domain.into(),
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
 pub struct CertificateData {
     pub name: String,
@@ -219,27 +148,473 @@ pub struct CertificateData {
     #[schema(value_type = String, format = Binary)]
     pub owner: String,
     pub metadata: Vec<String>,
+    /// Manufacturer to sign as, defaults to the one whose key is `owner`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manufacturer: Option<String>,
 }
 
 impl TryFrom<CertificateData> for Certificate {
     type Error = anyhow::Error;
     fn try_from(dto: CertificateData) -> Result<Self, Self::Error> {
-        
-        Ok(Certificate {
-            name: dto.name,
-            unique_id: dto.unique_id,
-            serial: dto.serial,
-            date: U256::from(dto.date),
-            owner: dto
-                .owner
-                .parse()
-                .map_err(|_| anyhow::anyhow!("Invalid address format"))?,
-            metadata_hash: to_meta_hash(&dto.metadata),
-            metadata: dto.metadata,
-        })
+        let owner = dto
+            .owner
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid address format"))?;
+
+        Ok(Certificate::new(dto.name, dto.unique_id, dto.serial, dto.date, owner, dto.metadata))
+    }
+}
+
+// How a certificate's QR code is rendered
+#[derive(
+    Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq, ToSchema, clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum QrFormat {
+    /// SVG document
+    #[default]
+    Svg,
+    /// Unicode block characters, for terminals and plain-text labels
+    Text,
+    /// PNG image, tagged with `dpi`
+    Png,
+    /// JPEG image, tagged with `dpi`
+    Jpeg,
+    /// Single page PDF the size of the code at `dpi`, drawn as vectors
+    Pdf,
+}
+
+impl QrFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            QrFormat::Svg => "image/svg+xml",
+            QrFormat::Text => "text/plain; charset=utf-8",
+            QrFormat::Png => "image/png",
+            QrFormat::Jpeg => "image/jpeg",
+            QrFormat::Pdf => "application/pdf",
+        }
+    }
+
+    // binary formats are base64 encoded wherever a QR code is embedded in JSON
+    pub fn is_binary(self) -> bool {
+        matches!(self, QrFormat::Png | QrFormat::Jpeg | QrFormat::Pdf)
+    }
+}
+
+// QR error correction, the share of the code that can be damaged and still scan
+#[derive(
+    Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq, ToSchema, clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum QrEcLevel {
+    /// ~7%
+    Low,
+    /// ~15%
+    #[default]
+    Medium,
+    /// ~25%
+    Quartile,
+    /// ~30%, for labels that get scratched or partly covered
+    High,
+}
+
+// An RGB color, written `#rrggbb`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QrColor(pub [u8; 3]);
+
+impl QrColor {
+    pub const BLACK: QrColor = QrColor([0, 0, 0]);
+    pub const WHITE: QrColor = QrColor([255, 255, 255]);
+}
+
+impl std::str::FromStr for QrColor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        let bytes = hex::decode(hex)
+            .ok()
+            .and_then(|bytes| <[u8; 3]>::try_from(bytes).ok())
+            .ok_or_else(|| format!("{:?} is not a #rrggbb color", s))?;
+        Ok(QrColor(bytes))
+    }
+}
+
+impl std::fmt::Display for QrColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", hex::encode(self.0))
+    }
+}
+
+impl Serialize for QrColor {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for QrColor {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// What a certificate's QR code holds
+#[derive(
+    Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq, ToSchema, clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum QrPayload {
+    /// The SignedCertificate JSON, readable by any scanner
+    #[default]
+    Json,
+    /// Versioned CBOR + base45 (`ERI1:...`), for certificates with rich metadata and
+    /// smaller, easier to scan codes. Read back with POST /qr_payload/decode
+    Compact,
+    /// A link to the configured `verify_url`, `{verify_url}/v/ERI1.<base64url>`, so a phone
+    /// camera opens the verdict page straight away
+    Url,
+}
+
+// How to encode and render a certificate's QR code. The same options are query
+// parameters on the HTTP routes and flags on the CLI
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, IntoParams, Validate, clap::Args)]
+#[into_params(parameter_in = Query)]
+pub struct QrQuery {
+    /// Format of the returned QR code, svg when omitted
+    #[serde(default)]
+    #[arg(long, value_enum, default_value_t = QrFormat::Svg)]
+    pub qr_format: QrFormat,
+    /// What the QR code holds, json when omitted
+    #[serde(default)]
+    #[arg(long, value_enum, default_value_t = QrPayload::Json)]
+    pub qr_payload: QrPayload,
+    /// Smallest width of the code in pixels, quiet zone included, 200 when omitted and at
+    /// most 2048.
+    /// Modules are whole pixels, so the code can come out slightly larger
+    #[serde(default = "default_qr_size")]
+    #[validate(range(min = 21, max = 2048))]
+    #[arg(long, default_value_t = default_qr_size())]
+    pub size: u32,
+    /// Print resolution, 300 when omitted. Written into PNG and JPEG files, and sets the
+    /// page size of a PDF
+    #[serde(default = "default_qr_dpi")]
+    #[validate(range(min = 72, max = 2400))]
+    #[arg(long, default_value_t = default_qr_dpi())]
+    pub dpi: u32,
+    /// Light border around the code in modules, 4 (the minimum the standard asks for)
+    /// when omitted. 0 when the label already leaves a margin
+    #[serde(default = "default_quiet_zone")]
+    #[validate(range(max = 16))]
+    #[arg(long, default_value_t = default_quiet_zone())]
+    pub quiet_zone: u32,
+    /// Error correction level, medium when omitted
+    #[serde(default)]
+    #[arg(long, value_enum, default_value_t = QrEcLevel::Medium)]
+    pub ec_level: QrEcLevel,
+    /// Module color as `#rrggbb`, black when omitted. Ignored for text
+    #[serde(default = "default_dark_color")]
+    #[param(value_type = String)]
+    #[arg(long, default_value_t = QrColor::BLACK)]
+    pub dark_color: QrColor,
+    /// Background color as `#rrggbb`, white when omitted. Ignored for text
+    #[serde(default = "default_light_color")]
+    #[param(value_type = String)]
+    #[arg(long, default_value_t = QrColor::WHITE)]
+    pub light_color: QrColor,
+}
+
+fn default_qr_size() -> u32 {
+    200
+}
+
+fn default_qr_dpi() -> u32 {
+    300
+}
+
+fn default_quiet_zone() -> u32 {
+    4
+}
+
+fn default_dark_color() -> QrColor {
+    QrColor::BLACK
+}
+
+fn default_light_color() -> QrColor {
+    QrColor::WHITE
+}
+
+impl Default for QrQuery {
+    fn default() -> Self {
+        QrQuery {
+            qr_format: QrFormat::default(),
+            qr_payload: QrPayload::default(),
+            size: default_qr_size(),
+            dpi: default_qr_dpi(),
+            quiet_zone: default_quiet_zone(),
+            ec_level: QrEcLevel::default(),
+            dark_color: default_dark_color(),
+            light_color: default_light_color(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct QrPayloadInput {
+    /// The text read from a certificate QR code, compact or JSON
+    pub payload: String,
+}
+
+// Paper the label sheet is printed on
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PageSize {
+    /// 210 x 297 mm
+    #[default]
+    A4,
+    /// 148 x 210 mm
+    A5,
+    /// 8.5 x 11 in
+    Letter,
+    /// 8.5 x 14 in
+    Legal,
+}
+
+impl PageSize {
+    // width and height in millimetres
+    pub fn dimensions_mm(self) -> (f32, f32) {
+        match self {
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::A5 => (148.0, 210.0),
+            PageSize::Letter => (215.9, 279.4),
+            PageSize::Legal => (215.9, 355.6),
+        }
+    }
+}
+
+// A line of text printed next to a label's QR code
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelField {
+    Name,
+    UniqueId,
+    Serial,
+    /// Certificate date as YYYY-MM-DD
+    Date,
+    Owner,
+    /// Name the signer is registered under on the Authenticity contract
+    Manufacturer,
+    /// Metadata entries separated by commas
+    Metadata,
+}
+
+// Layout of a label sheet: the page is split into a `columns` x `rows` grid inside the
+// margins, each label has the QR code on the left and the fields on the right
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema, Validate)]
+pub struct LabelTemplate {
+    #[serde(default)]
+    pub page_size: PageSize,
+    /// Custom page width for label rolls, overrides `page_size` together with `page_height_mm`
+    #[validate(range(min = 20.0, max = 1000.0))]
+    pub page_width_mm: Option<f32>,
+    #[validate(range(min = 20.0, max = 1000.0))]
+    pub page_height_mm: Option<f32>,
+    /// 3 when omitted
+    #[serde(default = "default_label_columns")]
+    #[validate(range(min = 1, max = 20))]
+    pub columns: u32,
+    /// 8 when omitted
+    #[serde(default = "default_label_rows")]
+    #[validate(range(min = 1, max = 40))]
+    pub rows: u32,
+    /// Blank border around the grid, 10 when omitted
+    #[serde(default = "default_label_margin")]
+    #[validate(range(min = 0.0, max = 100.0))]
+    pub margin_mm: f32,
+    /// Space between neighbouring labels, 2 when omitted
+    #[serde(default = "default_label_gap")]
+    #[validate(range(min = 0.0, max = 50.0))]
+    pub gap_mm: f32,
+    /// Printed top to bottom, name, serial and manufacturer when omitted
+    #[serde(default = "default_label_fields")]
+    pub fields: Vec<LabelField>,
+    /// 7 when omitted. Lines that don't fit the label are dropped, long ones are cut short
+    #[serde(default = "default_label_font_size")]
+    #[validate(range(min = 4.0, max = 36.0))]
+    pub font_size_pt: f32,
+    /// Outline every label, for cutting sheets printed on plain paper
+    #[serde(default)]
+    pub outline: bool,
+    /// What the QR codes hold, json when omitted. Compact codes stay readable on small labels
+    #[serde(default)]
+    pub qr_payload: QrPayload,
+    #[serde(default)]
+    pub ec_level: QrEcLevel,
+    /// Light border around each code in modules, 2 when omitted
+    #[serde(default = "default_label_quiet_zone")]
+    #[validate(range(max = 16))]
+    pub quiet_zone: u32,
+}
+
+fn default_label_columns() -> u32 {
+    3
+}
+
+fn default_label_rows() -> u32 {
+    8
+}
+
+fn default_label_margin() -> f32 {
+    10.0
+}
+
+fn default_label_gap() -> f32 {
+    2.0
+}
+
+fn default_label_fields() -> Vec<LabelField> {
+    vec![LabelField::Name, LabelField::Serial, LabelField::Manufacturer]
+}
+
+fn default_label_font_size() -> f32 {
+    7.0
+}
+
+fn default_label_quiet_zone() -> u32 {
+    2
+}
+
+impl Default for LabelTemplate {
+    fn default() -> Self {
+        LabelTemplate {
+            page_size: PageSize::default(),
+            page_width_mm: None,
+            page_height_mm: None,
+            columns: default_label_columns(),
+            rows: default_label_rows(),
+            margin_mm: default_label_margin(),
+            gap_mm: default_label_gap(),
+            fields: default_label_fields(),
+            font_size_pt: default_label_font_size(),
+            outline: false,
+            qr_payload: QrPayload::default(),
+            ec_level: QrEcLevel::default(),
+            quiet_zone: default_label_quiet_zone(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct LabelSheetInput {
+    /// Printed in order, left to right and top to bottom
+    pub certificates: Vec<SignedCertificate>,
+    #[serde(default)]
+    pub template: LabelTemplate,
+}
+
+// A freshly issued certificate, ready to print or hand to the buyer
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct IssuedCertificate {
+    pub certificate: SignedCertificate,
+    /// keccak256 of the abi encoded metadata, as the contract computes it
+    #[schema(value_type = String)]
+    pub metadata_hash: H256,
+    pub manufacturer: String,
+    #[schema(value_type = String, format = Binary)]
+    pub signer: Address,
+    pub qr_format: QrFormat,
+    pub qr_payload: QrPayload,
+    /// The QR code encoding `certificate`, in `qr_format`. PNG, JPEG and PDF codes are
+    /// base64 encoded
+    pub qr: String,
+}
+
+// One line of a bulk issuance response, `line` is the row's line number in the upload
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct BulkIssueLine {
+    pub line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<IssuedCertificate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorResponse>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct RowError {
+    pub line: usize,
+    pub message: String,
+}
+
+// Returned instead of the stream when any row fails validation, nothing is signed then
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct BulkValidationFailed {
+    pub code: String,
+    pub message: String,
+    pub rows: Vec<RowError>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct IssueBatchInput {
+    /// Manufacturer's label for the lot, signed along with the Merkle root
+    pub lot: String,
+    /// Manufacturer to sign as, defaults to the one whose key owns the certificates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manufacturer: Option<String>,
+    /// All owned by the same manufacturer
+    pub certificates: Vec<CertificateData>,
+}
+
+// One certificate of a signed batch, with everything needed to verify it on its own
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema, Validate)]
+pub struct BatchedCertificate {
+    #[validate(length(min = 1))]
+    pub name: String,
+    #[validate(length(min = 1))]
+    pub unique_id: String,
+    #[validate(length(min = 1))]
+    pub serial: String,
+    pub date: u64,
+    #[validate(custom(function = "validate_address"))]
+    #[schema(value_type = String, format = Binary)]
+    pub owner: String,
+    #[validate(length(min = 1))]
+    pub metadata: Vec<String>,
+    /// Sibling hashes from this certificate's leaf up to the batch root
+    #[schema(value_type = Vec<String>)]
+    pub proof: Vec<H256>,
+    /// The lot's root and its EIP-712 CertificateBatch signature
+    #[schema(value_type = Object)]
+    pub batch: SignedCertificateBatch,
+}
+
+impl TryFrom<&BatchedCertificate> for Certificate {
+    type Error = anyhow::Error;
+    fn try_from(dto: &BatchedCertificate) -> Result<Self, Self::Error> {
+        let owner = dto
+            .owner
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid address format"))?;
+
+        Ok(Certificate::new(
+            dto.name.clone(),
+            dto.unique_id.clone(),
+            dto.serial.clone(),
+            dto.date,
+            owner,
+            dto.metadata.clone(),
+        ))
     }
 }
 
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct IssuedBatch {
+    #[schema(value_type = Object)]
+    pub batch: SignedCertificateBatch,
+    pub manufacturer: String,
+    /// In request order
+    pub certificates: Vec<BatchedCertificate>,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
 pub struct RegInput {
     pub name: String,
@@ -1,33 +1,91 @@
 use ethabi::ethereum_types::{Address, H256};
 use ethers::contract::EthEvent;
+use ethers::utils::keccak256;
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+
+// Solidity only logs the keccak256 hash of an indexed string, so the *_hash fields
+// below can be compared against a candidate value but never decoded back to it
 
 #[derive(Debug, Clone, EthEvent, Serialize, Deserialize)]
-#[ethevent(name = "ManufacturerRegistered", abi = "ManufacturerRegistered(address,address)")]
+#[ethevent(name = "ManufacturerRegistered", abi = "ManufacturerRegistered(address,string)")]
 #[derive(Default)]
 pub struct ManufacturerRegistered {
-    
-    // pub name: String,
-
     #[ethevent(indexed)]
     pub manufacturer_address: Address,
 
     #[ethevent(indexed)]
-    pub manufacturer_name: String,
+    pub manufacturer_name_hash: H256,
 }
 
 impl ManufacturerRegistered {
-    pub fn init() -> Self {
-        Self {
-            manufacturer_address: Address::zero(),
-            manufacturer_name: String::new(),
-        }
-    }
-    pub fn new(manufacturer_address: Address, manufacturer_name: String) -> Self {
-        Self {
-            manufacturer_address,
-            manufacturer_name,
-        }
+    pub fn is_name(&self, name: &str) -> bool {
+        H256::from(keccak256(name.as_bytes())) == self.manufacturer_name_hash
     }
 }
+
+// Emitted by both contracts from their constructors, not consumed by the server yet
+#[allow(dead_code)]
+#[derive(Debug, Clone, EthEvent, Serialize, Deserialize, Default)]
+#[ethevent(name = "ContractCreated", abi = "ContractCreated(address,address)")]
+pub struct ContractCreated {
+    #[ethevent(indexed)]
+    pub contract_address: Address,
+
+    #[ethevent(indexed)]
+    pub owner: Address,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, EthEvent, Serialize, Deserialize, Default)]
+#[ethevent(name = "UserRegistered", abi = "UserRegistered(address,string)")]
+pub struct UserRegistered {
+    #[ethevent(indexed)]
+    pub user_address: Address,
+
+    #[ethevent(indexed)]
+    pub username_hash: H256,
+}
+
+#[derive(Debug, Clone, EthEvent, Serialize, Deserialize, Default)]
+#[ethevent(name = "OwnershipCode", abi = "OwnershipCode(bytes32,address)")]
+pub struct OwnershipCode {
+    #[ethevent(indexed)]
+    pub ownership_code: H256,
+
+    #[ethevent(indexed)]
+    pub temp_owner: Address,
+}
+
+#[derive(Debug, Clone, EthEvent, Serialize, Deserialize, Default)]
+#[ethevent(name = "OwnershipClaimed", abi = "OwnershipClaimed(address,address)")]
+pub struct OwnershipClaimed {
+    #[ethevent(indexed)]
+    pub new_owner: Address,
+
+    #[ethevent(indexed)]
+    pub old_owner: Address,
+}
+
+#[derive(Debug, Clone, EthEvent, Serialize, Deserialize, Default)]
+#[ethevent(name = "CodeRevoked", abi = "CodeRevoked(bytes32)")]
+pub struct CodeRevoked {
+    #[ethevent(indexed)]
+    pub item_hash: H256,
+}
+
+#[derive(Debug, Clone, EthEvent, Serialize, Deserialize, Default)]
+#[ethevent(name = "ItemCreated", abi = "ItemCreated(string,address)")]
+pub struct ItemCreated {
+    #[ethevent(indexed)]
+    pub item_id_hash: H256,
+
+    #[ethevent(indexed)]
+    pub owner: Address,
+}
+
+#[derive(Debug, Clone, EthEvent, Serialize, Deserialize, Default)]
+#[ethevent(name = "AuthenticitySet", abi = "AuthenticitySet(address)")]
+pub struct AuthenticitySet {
+    #[ethevent(indexed)]
+    pub authenticity_address: Address,
+}
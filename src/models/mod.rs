@@ -1,3 +1,5 @@
 pub(crate) mod certificate_model;
 pub(crate) mod events;
+pub(crate) mod ownership_model;
 pub(crate) mod router_path;
+pub(crate) mod verification_model;
@@ -0,0 +1,159 @@
+use crate::config::app_router::ownership;
+use crate::error::ApiError;
+use ethabi::ethereum_types::{Address, H256};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct UserRegInput {
+    pub username: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct UserProfile {
+    #[schema(value_type = String, format = Binary)]
+    pub user_address: Address,
+    pub username: String,
+    pub is_registered: bool,
+    pub registered_at: u64,
+}
+
+impl From<ownership::UserProfile> for UserProfile {
+    fn from(user: ownership::UserProfile) -> Self {
+        Self {
+            user_address: user.user_address,
+            username: user.username,
+            is_registered: user.is_registered,
+            registered_at: user.registered_at.low_u64(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct Item {
+    pub name: String,
+    pub item_id: String,
+    pub serial: String,
+    pub date: u64,
+    #[schema(value_type = String, format = Binary)]
+    pub owner: Address,
+    pub manufacturer: String,
+    pub metadata: Vec<String>,
+}
+
+impl TryFrom<ownership::Item> for Item {
+    type Error = ApiError;
+    fn try_from(item: ownership::Item) -> Result<Self, Self::Error> {
+        // the contract stores any uint256, refuse to silently drop the high bits
+        let date = u64::try_from(item.date).map_err(|_| {
+            ApiError::internal(format!(
+                "item {:?} has a date that does not fit in 64 bits: {}",
+                item.item_id, item.date
+            ))
+        })?;
+
+        Ok(Self {
+            name: item.name,
+            item_id: item.item_id,
+            serial: item.serial,
+            date,
+            owner: item.owner,
+            manufacturer: item.manufacturer,
+            metadata: item.metadata,
+        })
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct Owner {
+    pub name: String,
+    pub item_id: String,
+    pub username: String,
+    #[schema(value_type = String, format = Binary)]
+    pub owner: Address,
+}
+
+impl From<ownership::Owner> for Owner {
+    fn from(owner: ownership::Owner) -> Self {
+        Self {
+            name: owner.name,
+            item_id: owner.item_id,
+            username: owner.username,
+            owner: owner.owner,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct GenerateCodeInput {
+    pub item_id: String,
+    #[schema(value_type = String, format = Binary)]
+    pub temp_owner: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct ItemHashInput {
+    #[schema(value_type = String, format = Binary)]
+    pub item_hash: String,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStatus {
+    CodeGenerated,
+    Claimed,
+    Revoked,
+}
+
+// What the transfer endpoints return once the transaction is mined
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct TransferRecord {
+    pub status: TransferStatus,
+    pub item_id: Option<String>,
+    #[schema(value_type = String, format = Binary)]
+    pub item_hash: H256,
+    #[schema(value_type = String, format = Binary, nullable = true)]
+    pub old_owner: Option<Address>,
+    #[schema(value_type = String, format = Binary, nullable = true)]
+    pub new_owner: Option<Address>,
+    #[schema(value_type = String, format = Binary)]
+    pub tx_hash: H256,
+    pub block_number: Option<u64>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProvenanceKind {
+    Created,
+    CodeIssued,
+    CodeRevoked,
+    Claimed,
+}
+
+// One step in an item's chain of custody
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct ProvenanceEvent {
+    pub kind: ProvenanceKind,
+    /// Who holds the item after this step
+    #[schema(value_type = String, format = Binary)]
+    pub owner: Address,
+    /// The temp owner of a code, or the previous owner for a claim
+    #[schema(value_type = String, format = Binary, nullable = true)]
+    pub counterparty: Option<Address>,
+    #[schema(value_type = String, format = Binary, nullable = true)]
+    pub item_hash: Option<H256>,
+    pub block_number: u64,
+    pub timestamp: u64,
+    #[schema(value_type = String, format = Binary)]
+    pub tx_hash: H256,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct ItemHistory {
+    pub item_id: String,
+    #[schema(value_type = String, format = Binary)]
+    pub current_owner: Address,
+    /// Events after this block are not in the history yet
+    pub indexed_to_block: Option<u64>,
+    pub events: Vec<ProvenanceEvent>,
+}
@@ -4,11 +4,35 @@ use serde::{Deserialize, Serialize};
 pub struct RouterPath {
     pub  generate_signature: String,
     pub verify_authenticity: String,
+    pub verify_authenticity_batch: String,
+    pub verify_qr: String,
+    pub verify_link: String,
+    pub manufacturer_cache: String,
     pub sign_up: String,
     pub get_owner: String,
     pub verify_signature: String,
     pub create_certificate: String,
-    pub qr_code: String
+    pub certificate_type: String,
+    pub qr_code: String,
+    pub decode_qr_payload: String,
+    pub label_sheets: String,
+    pub user_registers: String,
+    pub get_user: String,
+    pub get_all_my_items: String,
+    pub get_item: String,
+    pub verify_ownership: String,
+    pub is_owner: String,
+    pub get_temp_owner: String,
+    pub generate_ownership_code: String,
+    pub claim_ownership: String,
+    pub revoke_ownership_code: String,
+    pub item_history: String,
+    pub registry_snapshot: String,
+    pub verify_offline: String,
+    pub issue_certificate: String,
+    pub issue_certificates_bulk: String,
+    pub certificate_batches: String,
+    pub verify_batched_certificate: String,
 }
 
 impl RouterPath {
@@ -16,11 +40,35 @@ impl RouterPath {
         Self {
             generate_signature: "/generate_signature".to_string(),
             verify_authenticity: "/verify_authenticity".to_string(),
+            verify_authenticity_batch: "/verify_authenticity/batch".to_string(),
+            verify_qr: "/verify/qr".to_string(),
+            verify_link: "/v/{payload}".to_string(),
+            manufacturer_cache: "/manufacturer_cache".to_string(),
             sign_up: "/manufacturer_registers".to_string(),
             get_owner: "/get_owner/{address}".to_string(),
             verify_signature: "/verify_signature".to_string(),
             create_certificate: "/create_certificate".to_string(),
+            certificate_type: "/certificate_type".to_string(),
             qr_code: "/qr_code".to_string(),
+            decode_qr_payload: "/qr_payload/decode".to_string(),
+            label_sheets: "/label_sheets".to_string(),
+            user_registers: "/user_registers".to_string(),
+            get_user: "/get_user/{address}".to_string(),
+            get_all_my_items: "/get_all_my_items".to_string(),
+            get_item: "/get_item/{item_id}".to_string(),
+            verify_ownership: "/verify_ownership/{item_id}".to_string(),
+            is_owner: "/is_owner/{address}/{item_id}".to_string(),
+            get_temp_owner: "/get_temp_owner/{item_hash}".to_string(),
+            generate_ownership_code: "/generate_ownership_code".to_string(),
+            claim_ownership: "/claim_ownership".to_string(),
+            revoke_ownership_code: "/revoke_ownership_code".to_string(),
+            item_history: "/items/{item_id}/history".to_string(),
+            registry_snapshot: "/registry_snapshot".to_string(),
+            verify_offline: "/verify_offline".to_string(),
+            issue_certificate: "/certificates".to_string(),
+            issue_certificates_bulk: "/certificates/bulk".to_string(),
+            certificate_batches: "/certificate_batches".to_string(),
+            verify_batched_certificate: "/certificate_batches/verify".to_string(),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,138 @@
+use crate::error::ErrorResponse;
+use crate::models::certificate_model::SignedCertificate;
+use eri_core::SignedRegistrySnapshot;
+use ethabi::ethereum_types::{Address, H256};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+// Why a certificate was judged not authentic
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum VerificationFailure {
+    /// A field failed validation (empty name, malformed owner, ...)
+    InvalidCertificate,
+    /// The signature is not valid hex, not a 64 or 65 byte signature, or is malleable (high s)
+    InvalidSignatureFormat,
+    /// No address could be recovered from the signature
+    SignerRecoveryFailed,
+    /// The signature was made by someone other than the certificate owner
+    SignerNotOwner,
+    /// The signer is not registered on the Authenticity contract
+    ManufacturerNotRegistered,
+    /// The inclusion proof does not lead to the signed batch root
+    NotInBatch,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct VerificationResult {
+    pub authentic: bool,
+    #[schema(value_type = String, format = Binary, nullable = true)]
+    pub signer: Option<Address>,
+    /// Owner as written on the certificate, kept verbatim when it is not a valid address
+    pub claimed_owner: String,
+    pub manufacturer_name: Option<String>,
+    pub failure_reason: Option<VerificationFailure>,
+    pub chain_id: u64,
+    #[schema(value_type = String, format = Binary)]
+    pub contract: Address,
+}
+
+impl VerificationResult {
+    pub fn authentic(
+        signer: Address,
+        claimed_owner: String,
+        manufacturer_name: String,
+        chain_id: u64,
+        contract: Address,
+    ) -> Self {
+        Self {
+            authentic: true,
+            signer: Some(signer),
+            claimed_owner,
+            manufacturer_name: Some(manufacturer_name),
+            failure_reason: None,
+            chain_id,
+            contract,
+        }
+    }
+
+    pub fn rejected(
+        reason: VerificationFailure,
+        signer: Option<Address>,
+        claimed_owner: String,
+        chain_id: u64,
+        contract: Address,
+    ) -> Self {
+        Self {
+            authentic: false,
+            signer,
+            claimed_owner,
+            manufacturer_name: None,
+            failure_reason: Some(reason),
+            chain_id,
+            contract,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct OfflineVerifyInput {
+    pub certificate: SignedCertificate,
+    /// Snapshot as returned by GET /registry_snapshot
+    #[schema(value_type = Object)]
+    pub snapshot: SignedRegistrySnapshot,
+    /// How old the snapshot may be before the verdict is flagged stale, defaults to a day
+    pub max_age_secs: Option<u64>,
+}
+
+// Verdict against a registry snapshot, with how far behind the chain it may be
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct OfflineVerificationResult {
+    #[serde(flatten)]
+    pub result: VerificationResult,
+    pub snapshot_block: u64,
+    #[schema(value_type = String, format = Binary)]
+    pub snapshot_block_hash: H256,
+    /// Seconds between the snapshot block and now
+    pub snapshot_age_secs: u64,
+    /// The snapshot is older than `max_age_secs`, registrations since then are not seen
+    pub stale: bool,
+}
+
+// Verdict for a certificate read from a photo of its QR code
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct QrVerificationResult {
+    /// The certificate the QR code holds
+    pub certificate: SignedCertificate,
+    #[serde(flatten)]
+    pub result: VerificationResult,
+}
+
+// Verdict for one certificate of a batch. `error` is set instead of `result` when
+// the chain could not be asked about this certificate's signer
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct BatchVerdict {
+    /// Position of the certificate in the request
+    pub index: usize,
+    pub result: Option<VerificationResult>,
+    pub error: Option<ErrorResponse>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct BatchVerificationResult {
+    pub total: usize,
+    pub authentic: usize,
+    pub rejected: usize,
+    /// Certificates left without a verdict because of an RPC failure
+    pub failed: usize,
+    pub verdicts: Vec<BatchVerdict>,
+}
+
+// Counters of the getManufacturer cache, misses include expired entries
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
+pub struct ManufacturerCacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub ttl_secs: u64,
+}
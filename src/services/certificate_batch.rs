@@ -0,0 +1,215 @@
+use crate::config::app_state::AppState;
+use crate::error::{ApiError, ErrorResponse};
+use crate::models::certificate_model::{
+    BatchedCertificate, BulkValidationFailed, Certificate, IssueBatchInput, IssuedBatch, RowError,
+};
+use crate::models::verification_model::{VerificationFailure, VerificationResult};
+use crate::services::issue_certificate::{MAX_BULK_ROWS, validate_rows};
+use crate::services::verify_authenticity::{Rejection, lookup_manufacturer, verdict};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Json, extract::State};
+use eri_core::{CertificateBatch, CertificateError, process_proof, verify_batched};
+use ethers::prelude::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+use validator::Validate;
+
+#[utoipa::path(
+    post,
+    path = "/certificate_batches",
+    request_body = IssueBatchInput,
+    responses(
+        (status = 200, description = "Merkle root over the lot signed once, every certificate returned with its inclusion proof", body = IssuedBatch),
+        (status = 400, description = "Empty lot name, or no / too many certificates", body = ErrorResponse),
+        (status = 403, description = "No signing key for this manufacturer", body = ErrorResponse),
+        (status = 422, description = "Some certificates are invalid, nothing was signed", body = BulkValidationFailed),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn issue_certificate_batch(
+    State(state): State<AppState>,
+    Json(input): Json<IssueBatchInput>,
+) -> Result<Response, ApiError> {
+    if input.lot.is_empty() {
+        return Err(ApiError::bad_request("Empty lot"));
+    }
+    if input.certificates.is_empty() || input.certificates.len() > MAX_BULK_ROWS {
+        return Err(ApiError::bad_request(format!(
+            "A batch holds 1 to {} certificates, got {}",
+            MAX_BULK_ROWS,
+            input.certificates.len()
+        )));
+    }
+
+    // `line` is the certificate's position in the request, counted from 1
+    let total = input.certificates.len();
+    let rows = input
+        .certificates
+        .into_iter()
+        .enumerate()
+        .map(|(index, cert)| (index + 1, Ok(cert)))
+        .collect();
+
+    let mut rows = validate_rows(&state, rows);
+    // one signature covers the whole lot, so every certificate needs the same owner
+    if let Ok(valid) = &rows {
+        let owner = &valid[0].1.owner;
+        let others: Vec<RowError> = valid
+            .iter()
+            .filter(|(_, cert)| !cert.owner.eq_ignore_ascii_case(owner))
+            .map(|(line, cert)| RowError {
+                line: *line,
+                message: format!(
+                    "owner {} differs from the batch owner {}",
+                    cert.owner, owner
+                ),
+            })
+            .collect();
+        if !others.is_empty() {
+            rows = Err(others);
+        }
+    }
+
+    let rows = match rows {
+        Ok(rows) => rows,
+        Err(rows) => {
+            let body = BulkValidationFailed {
+                code: "INVALID_ROWS".to_string(),
+                message: format!(
+                    "{} of {} certificates are invalid, nothing was signed",
+                    rows.len(),
+                    total
+                ),
+                rows,
+            };
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Json(body)).into_response());
+        }
+    };
+
+    let domain = state.eip712_domain();
+    let certificates = rows
+        .iter()
+        .map(|(_, cert)| {
+            Certificate::try_from(cert.clone())
+                .map(|certificate| certificate.with_domain(domain.clone()))
+                .map_err(ApiError::bad_request)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let owner = certificates[0].owner;
+    let (manufacturer, signer) = state
+        .keys
+        .signer_for(input.manufacturer.as_deref(), owner)?;
+
+    let date = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(ApiError::internal)?
+        .as_secs();
+    let (batch, tree) =
+        CertificateBatch::new(input.lot, date, owner, &certificates).map_err(ApiError::internal)?;
+
+    let signed = signer
+        .sign_batch(batch.with_domain(domain))
+        .await
+        .map_err(|e| ApiError::internal(format!("Signature error: {:?}", e)))?;
+
+    // the root signature is checked once, then every proof against the root
+    let recovered = eri_core::parse_signature(&signed.signature)
+        .and_then(|signature| Ok(signature.recover(signed.batch.digest()?)?))
+        .map_err(ApiError::internal)?;
+    if recovered != signer.address() {
+        return Err(ApiError::internal(format!(
+            "batch signature recovers to {:?} instead of {:?}",
+            recovered,
+            signer.address()
+        )));
+    }
+
+    let items = rows
+        .into_iter()
+        .zip(&certificates)
+        .enumerate()
+        .map(|(index, ((_, cert), certificate))| {
+            let proof = tree.proof(index);
+            if process_proof(eri_core::merkle_leaf(certificate), &proof) != signed.batch.root {
+                return Err(ApiError::internal(format!(
+                    "proof for certificate {} does not lead to the root",
+                    cert.unique_id
+                )));
+            }
+
+            Ok(BatchedCertificate {
+                name: cert.name,
+                unique_id: cert.unique_id,
+                serial: cert.serial,
+                date: cert.date,
+                owner: format!("{:?}", owner),
+                metadata: cert.metadata,
+                proof,
+                batch: signed.clone(),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    println!(
+        "🧾 Signed batch {:?} of {} certificates for {}, root {:?}",
+        signed.batch.lot, signed.batch.count, manufacturer, signed.batch.root
+    );
+
+    Ok(Json(IssuedBatch {
+        batch: signed,
+        manufacturer: manufacturer.to_string(),
+        certificates: items,
+    })
+    .into_response())
+}
+
+// Everything about a batched certificate that can be checked without the chain
+fn recover_batch_signer(cert: &BatchedCertificate, state: &AppState) -> Result<Address, Rejection> {
+    if let Err(errors) = cert.validate() {
+        eprintln!("Invalid batched certificate: {}", errors);
+        return Err((VerificationFailure::InvalidCertificate, None));
+    }
+
+    let certificate = Certificate::try_from(cert)
+        .map_err(|e| {
+            eprintln!("Certificate conversion error: {:?}", e);
+            (VerificationFailure::InvalidCertificate, None)
+        })?
+        .with_domain(state.eip712_domain());
+
+    verify_batched(&certificate, &cert.proof, &cert.batch).map_err(|e| match e {
+        CertificateError::NotInBatch { .. } => (VerificationFailure::NotInBatch, None),
+        CertificateError::SignerNotOwner { signer, .. } => {
+            (VerificationFailure::SignerNotOwner, Some(signer))
+        }
+        CertificateError::InvalidSignatureFormat(_) => {
+            (VerificationFailure::InvalidSignatureFormat, None)
+        }
+        e => {
+            eprintln!("Batch signer recovery error: {:?}", e);
+            (VerificationFailure::SignerRecoveryFailed, None)
+        }
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/certificate_batches/verify",
+    request_body = BatchedCertificate,
+    responses(
+        (status = 200, description = "Verdict for one certificate of a signed batch", body = VerificationResult),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
+    )
+)]
+pub async fn verify_batched_certificate(
+    State(state): State<AppState>,
+    Json(cert): Json<BatchedCertificate>,
+) -> Result<Json<VerificationResult>, ApiError> {
+    let recovered = match recover_batch_signer(&cert, &state) {
+        Ok(signer) => Ok((signer, lookup_manufacturer(&state, signer).await?)),
+        Err(rejection) => Err(rejection),
+    };
+
+    Ok(Json(verdict(&state, cert.owner.clone(), recovered)))
+}
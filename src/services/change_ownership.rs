@@ -0,0 +1,193 @@
+use crate::config::app_router::Ownership;
+use crate::config::app_state::{AppState, EthClient};
+use crate::error::{ApiError, ErrorResponse};
+use crate::models::events::{CodeRevoked, OwnershipClaimed, OwnershipCode};
+use crate::models::ownership_model::{
+    GenerateCodeInput, ItemHashInput, TransferRecord, TransferStatus,
+};
+use crate::utility::{self, decode_receipt_event, parse_item_hash};
+use axum::{Json, extract::State};
+use ethers::prelude::*;
+use ethers::signers::Signer;
+
+type OwnershipCall = ContractCall<EthClient, ()>;
+
+// Send the transaction and wait for a successful receipt
+async fn send_and_confirm(call: OwnershipCall) -> Result<TransactionReceipt, ApiError> {
+    let receipt = call
+        .send()
+        .await?
+        .await?
+        .ok_or_else(|| ApiError::Rpc("transaction dropped from the mempool".to_string()))?;
+
+    if receipt.status != Some(1.into()) {
+        return Err(ApiError::Reverted(format!(
+            "transaction {:?} reverted",
+            receipt.transaction_hash
+        )));
+    }
+
+    Ok(receipt)
+}
+
+// The id of the server wallet's item whose change of ownership code, issued while
+// `issued_by` held it, is `item_hash`. getAllMyItems reads msg.sender, the wallet behind
+// eth_client
+async fn held_item_id(
+    contract: &Ownership<EthClient>,
+    item_hash: H256,
+    issued_by: Address,
+) -> Result<Option<String>, ApiError> {
+    let items = contract.get_all_my_items().call().await?;
+
+    Ok(items
+        .into_iter()
+        .find(|item| {
+            let mut item = item.clone();
+            item.owner = issued_by;
+            utility::item_hash(&item) == item_hash
+        })
+        .map(|item| item.item_id))
+}
+
+#[utoipa::path(
+    post,
+    path = "/generate_ownership_code",
+    request_body = GenerateCodeInput,
+    responses(
+        (status = 200, description = "Change of ownership code generated", body = TransferRecord),
+        (status = 400, description = "Invalid input", body = ErrorResponse),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
+    )
+)]
+pub async fn generate_ownership_code(
+    State(state): State<AppState>,
+    Json(input): Json<GenerateCodeInput>,
+) -> Result<Json<TransferRecord>, ApiError> {
+    let temp_owner: Address = input
+        .temp_owner
+        .parse()
+        .map_err(|e| ApiError::bad_request(format!("Invalid temp owner address: {:?}", e)))?;
+
+    let contract = Ownership::new(state.ownership_contract, state.eth_client.clone());
+
+    let receipt = send_and_confirm(
+        contract.generate_change_of_ownership_code(input.item_id.clone(), temp_owner),
+    )
+    .await?;
+
+    let event: OwnershipCode = decode_receipt_event(&receipt, state.ownership_contract)
+        .ok_or_else(|| {
+            ApiError::internal(format!(
+                "OwnershipCode event not found in {:?}",
+                receipt.transaction_hash
+            ))
+        })?;
+
+    println!("🔑 Ownership code generated:");
+    println!("    Item Hash: {:?}", event.ownership_code);
+    println!("    Temp Owner: {:?}", event.temp_owner);
+
+    Ok(Json(TransferRecord {
+        status: TransferStatus::CodeGenerated,
+        item_id: Some(input.item_id),
+        item_hash: event.ownership_code,
+        old_owner: Some(state.eth_client.signer().address()),
+        new_owner: Some(event.temp_owner),
+        tx_hash: receipt.transaction_hash,
+        block_number: receipt.block_number.map(|n| n.as_u64()),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/claim_ownership",
+    request_body = ItemHashInput,
+    responses(
+        (status = 200, description = "Ownership claimed by the server wallet", body = TransferRecord),
+        (status = 400, description = "Invalid input", body = ErrorResponse),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
+    )
+)]
+pub async fn claim_ownership(
+    State(state): State<AppState>,
+    Json(input): Json<ItemHashInput>,
+) -> Result<Json<TransferRecord>, ApiError> {
+    let item_hash = parse_item_hash(&input.item_hash)?;
+
+    let contract = Ownership::new(state.ownership_contract, state.eth_client.clone());
+
+    let receipt = send_and_confirm(contract.new_owner_claim_ownership(item_hash.into())).await?;
+
+    let event: OwnershipClaimed = decode_receipt_event(&receipt, state.ownership_contract)
+        .ok_or_else(|| {
+            ApiError::internal(format!(
+                "OwnershipClaimed event not found in {:?}",
+                receipt.transaction_hash
+            ))
+        })?;
+
+    // the claim moved the item to the server wallet, its code was issued by the old owner
+    let item_id = held_item_id(&contract, item_hash, event.old_owner).await?;
+
+    println!("📦 Ownership claimed:");
+    println!("    New Owner: {:?}", event.new_owner);
+    println!("    Old Owner: {:?}", event.old_owner);
+
+    Ok(Json(TransferRecord {
+        status: TransferStatus::Claimed,
+        item_id,
+        item_hash,
+        old_owner: Some(event.old_owner),
+        new_owner: Some(event.new_owner),
+        tx_hash: receipt.transaction_hash,
+        block_number: receipt.block_number.map(|n| n.as_u64()),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/revoke_ownership_code",
+    request_body = ItemHashInput,
+    responses(
+        (status = 200, description = "Change of ownership code revoked", body = TransferRecord),
+        (status = 400, description = "Invalid input", body = ErrorResponse),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
+    )
+)]
+pub async fn revoke_ownership_code(
+    State(state): State<AppState>,
+    Json(input): Json<ItemHashInput>,
+) -> Result<Json<TransferRecord>, ApiError> {
+    let item_hash = parse_item_hash(&input.item_hash)?;
+
+    let contract = Ownership::new(state.ownership_contract, state.eth_client.clone());
+
+    // the code is deleted by the revoke, so read who it pointed to beforehand
+    let temp_owner = contract.get_temp_owner(item_hash.into()).call().await?;
+
+    let receipt = send_and_confirm(contract.owner_revoke_code(item_hash.into())).await?;
+
+    let event: CodeRevoked =
+        decode_receipt_event(&receipt, state.ownership_contract).ok_or_else(|| {
+            ApiError::internal(format!(
+                "CodeRevoked event not found in {:?}",
+                receipt.transaction_hash
+            ))
+        })?;
+
+    let item_id = held_item_id(&contract, item_hash, state.eth_client.signer().address()).await?;
+
+    println!("🚫 Ownership code revoked:");
+    println!("    Item Hash: {:?}", event.item_hash);
+
+    Ok(Json(TransferRecord {
+        status: TransferStatus::Revoked,
+        item_id,
+        item_hash: event.item_hash,
+        old_owner: Some(state.eth_client.signer().address()),
+        new_owner: (!temp_owner.is_zero()).then_some(temp_owner),
+        tx_hash: receipt.transaction_hash,
+        block_number: receipt.block_number.map(|n| n.as_u64()),
+    }))
+}
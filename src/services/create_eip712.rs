@@ -1,83 +1,78 @@
 use crate::models::certificate_model::{
-    Certificate, CertificateData, CustomEIP712Domain, Eip712Object,
+    Certificate, CertificateData, CertificateType, CustomEIP712Domain, Eip712Object,
 };
-use crate::utility::to_meta_hash;
-use axum::{Json, http::StatusCode};
+use eri_core::TypedStruct;
+use crate::config::app_state::AppState;
+use crate::error::{ApiError, ErrorResponse};
+use axum::{Json, extract::State};
 use ethers::types::transaction::eip712::Eip712;
-use ethers::utils::hex::ToHexExt;
-use ethers::utils::keccak256;
-use ethers::{contract::EthEvent, prelude::*, signers::Signer};
-use std::error::Error;
 
-#[utoipa::path(
-    post,
-    path = "/create_certificate",
-    request_body = CertificateData,
-    responses(
-        (status = 200, description = "EIP-712 object created successfully", body = Eip712Object),
-        (status = 400, description = "Invalid input"),
-        (status = 500, description = "Internal server error")
-    )
-)]
-pub async fn create_certificate(
-    Json(cert): Json<CertificateData>,
-) -> Result<Json<Eip712Object>, StatusCode> {
+// The EIP-712 object a wallet signs for `cert`, under the server's domain
+pub(crate) fn typed_data(
+    state: &AppState,
+    cert: CertificateData,
+) -> Result<Eip712Object, ApiError> {
     // Validate inputs
     if cert.name.is_empty() || cert.unique_id.is_empty() || cert.serial.is_empty() {
-        eprintln!("Empty name, unique_id, or serial");
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(ApiError::bad_request("Empty name, unique_id, or serial"));
     }
     if cert.owner.is_empty() {
-        eprintln!("Empty manufacturer_address");
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(ApiError::bad_request("Empty manufacturer_address"));
     }
 
-    println!("owner: {:?}", cert.owner);
+    eprintln!("owner: {:?}", cert.owner);
 
     // Convert to Certificate
-    let certificate: Certificate = cert.try_into().map_err(|e| {
-        eprintln!("Certificate conversion error: {:?}", e);
-        StatusCode::BAD_REQUEST
-    })?;
+    let certificate = Certificate::try_from(cert)
+        .map_err(|e| ApiError::bad_request(format!("Certificate conversion error: {:?}", e)))?
+        .with_domain(state.eip712_domain());
 
     // Create EIP-712 domain
-    let domain = certificate.domain().map_err(|e| {
-        eprintln!("EIP-712 domain error: {:?}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let domain = certificate
+        .domain()
+        .map_err(|e| ApiError::internal(format!("EIP-712 domain error: {:?}", e)))?;
 
     // Convert to CustomEIP712Domain
     let custom_domain = CustomEIP712Domain::from(domain);
 
-    // Define EIP-712 types
-    let types = serde_json::json!({
-        "Certificate": [
-            { "name": "name", "type": "string" },
-            { "name": "uniqueId", "type": "string" },
-            { "name": "serial", "type": "string" },
-            { "name": "date", "type": "uint256" },
-            { "name": "owner", "type": "address" },
-            { "name": "metadataHash", "type": "bytes32" }
-        ]
-    });
+    // types and value come from the same field list the struct hash is computed from
+    let types = Certificate::types_json();
+    let value = serde_json::Value::Object(certificate.field_values());
 
-    let metadata_hash = to_meta_hash(&certificate.metadata);
-    // Create EIP-712 value
-    let value = serde_json::json!({
-        "name": certificate.name,
-        "uniqueId": certificate.unique_id,
-        "serial": certificate.serial,
-        "date": certificate.date.to_string(),
-        "owner": ToHexExt::encode_hex_upper_with_prefix(&certificate.owner),
-        "metadataHash": Bytes::from(metadata_hash.to_vec()),
-    });
-
-    let eip712_object = Eip712Object {
+    Ok(Eip712Object {
         domain: custom_domain,
         types,
         value,
-    };
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/create_certificate",
+    request_body = CertificateData,
+    responses(
+        (status = 200, description = "EIP-712 object created successfully", body = Eip712Object),
+        (status = 400, description = "Invalid input", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn create_certificate(
+    State(state): State<AppState>,
+    Json(cert): Json<CertificateData>,
+) -> Result<Json<Eip712Object>, ApiError> {
+    let eip712_object = typed_data(&state, cert)?;
 
     eprintln!("EIP-712 object created: {:?}", eip712_object);
     Ok(Json(eip712_object))
 }
+
+#[utoipa::path(
+    get,
+    path = "/certificate_type",
+    responses(
+        (status = 200, description = "EIP-712 Certificate type and domain the server signs and verifies under", body = CertificateType)
+    )
+)]
+pub async fn certificate_type(State(state): State<AppState>) -> Json<CertificateType> {
+    Json(CertificateType::new(state.eip712_domain()))
+}
@@ -0,0 +1,540 @@
+use crate::config::app_state::AppState;
+use crate::error::{ApiError, ErrorResponse};
+use crate::models::certificate_model::{
+    BulkIssueLine, BulkValidationFailed, Certificate, CertificateData, QrQuery,
+    IssuedCertificate, RowError, SignedCertificate,
+};
+use crate::services::qr_code::render_qr_limited;
+use crate::services::verify_authenticity::recover_signer;
+use axum::body::Body;
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use ethers::prelude::*;
+use futures::{Stream, StreamExt, stream};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use validator::Validate;
+
+pub(crate) const MAX_BULK_ROWS: usize = 50_000;
+// certificates being signed at once, each on its own task so they spread over the worker threads
+const SIGN_CONCURRENCY: usize = 32;
+
+// Everything about `cert` that can be refused before signing it
+fn prepare(state: &AppState, cert: &CertificateData) -> Result<Certificate, ApiError> {
+    if cert.name.is_empty() || cert.unique_id.is_empty() || cert.serial.is_empty() {
+        return Err(ApiError::bad_request("Empty name, unique_id, or serial"));
+    }
+    if cert.metadata.is_empty() {
+        return Err(ApiError::bad_request("Empty metadata"));
+    }
+
+    let certificate = Certificate::try_from(cert.clone())
+        .map_err(ApiError::bad_request)?
+        .with_domain(state.eip712_domain());
+
+    // each manufacturer signs with its own key, never with another's
+    state
+        .keys
+        .signer_for(cert.manufacturer.as_deref(), certificate.owner)?;
+
+    Ok(certificate)
+}
+
+// Signs `cert` with its manufacturer's key and checks the result verifies, without the QR
+pub(crate) async fn sign_certificate_data(
+    state: &AppState,
+    cert: CertificateData,
+) -> Result<(SignedCertificate, Certificate, String), ApiError> {
+    let certificate = prepare(state, &cert)?;
+    let (manufacturer, signer) = state
+        .keys
+        .signer_for(cert.manufacturer.as_deref(), certificate.owner)?;
+    let signature = signer
+        .sign_certificate(&certificate)
+        .await
+        .map_err(|e| ApiError::internal(format!("Signature error: {:?}", e)))?;
+
+    let signed = SignedCertificate {
+        name: cert.name,
+        unique_id: cert.unique_id,
+        serial: cert.serial,
+        date: cert.date,
+        owner: format!("{:?}", certificate.owner),
+        metadata: cert.metadata,
+        signature: format!("0x{}", signature),
+    };
+
+    // run the certificate through the same checks /verify_authenticity does, so nothing
+    // that would be rejected there is ever handed out
+    match recover_signer(&signed, state.eip712_domain()) {
+        Ok((_, recovered)) if recovered == signer.address() => {}
+        Ok((_, recovered)) => {
+            return Err(ApiError::internal(format!(
+                "issued certificate recovers to {:?} instead of {:?}",
+                recovered,
+                signer.address()
+            )));
+        }
+        Err((reason, _)) => {
+            return Err(ApiError::internal(format!(
+                "issued certificate does not verify: {:?}",
+                reason
+            )));
+        }
+    }
+
+    Ok((signed, certificate, manufacturer.to_string()))
+}
+
+pub(crate) async fn issue(
+    state: &AppState,
+    cert: CertificateData,
+    qr: QrQuery,
+) -> Result<IssuedCertificate, ApiError> {
+    let (signed, certificate, manufacturer) = sign_certificate_data(state, cert).await?;
+    let rendered =
+        render_qr_limited(signed.clone(), qr, state.config.server.verify_url.clone()).await?;
+
+    Ok(IssuedCertificate {
+        certificate: signed,
+        metadata_hash: H256::from(certificate.metadata_hash),
+        manufacturer,
+        signer: certificate.owner,
+        qr_format: qr.qr_format,
+        qr_payload: qr.qr_payload,
+        qr: rendered.into_text(),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/certificates",
+    request_body = CertificateData,
+    params(QrQuery),
+    responses(
+        (status = 200, description = "Certificate signed, self-verified and rendered as a QR code", body = IssuedCertificate),
+        (status = 400, description = "Invalid input or certificate too large for a QR code", body = ErrorResponse),
+        (status = 403, description = "No signing key for this manufacturer", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn issue_certificate(
+    State(state): State<AppState>,
+    Query(query): Query<QrQuery>,
+    Json(cert): Json<CertificateData>,
+) -> Result<Json<IssuedCertificate>, ApiError> {
+    let issued = issue(&state, cert, query).await?;
+
+    println!(
+        "🧾 Issued certificate {} for {}",
+        issued.certificate.unique_id, issued.manufacturer
+    );
+
+    Ok(Json(issued))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum BulkFormat {
+    /// A header row, then one certificate per row. `metadata` entries are separated by `|`
+    Csv,
+    /// One CertificateData JSON object per line
+    Ndjson,
+}
+
+impl BulkFormat {
+    pub fn from_content_type(headers: &HeaderMap) -> Result<Self, ApiError> {
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+
+        match content_type.split(';').next().unwrap_or_default().trim() {
+            "text/csv" => Ok(BulkFormat::Csv),
+            "application/x-ndjson" | "application/jsonl" | "application/json-lines" => {
+                Ok(BulkFormat::Ndjson)
+            }
+            other => Err(ApiError::bad_request(format!(
+                "Unsupported content type {:?}, send text/csv or application/x-ndjson",
+                other
+            ))),
+        }
+    }
+
+    // by file extension, JSON Lines unless it ends in .csv
+    pub fn from_path(path: &str) -> Self {
+        if path.to_ascii_lowercase().ends_with(".csv") {
+            BulkFormat::Csv
+        } else {
+            BulkFormat::Ndjson
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CsvRow {
+    name: String,
+    unique_id: String,
+    serial: String,
+    date: u64,
+    owner: String,
+    metadata: String,
+    #[serde(default)]
+    manufacturer: Option<String>,
+}
+
+impl From<CsvRow> for CertificateData {
+    fn from(row: CsvRow) -> Self {
+        CertificateData {
+            name: row.name,
+            unique_id: row.unique_id,
+            serial: row.serial,
+            date: row.date,
+            owner: row.owner,
+            // an empty cell is no metadata, not one empty entry
+            metadata: match row.metadata.as_str() {
+                "" => Vec::new(),
+                metadata => metadata.split('|').map(str::to_string).collect(),
+            },
+            manufacturer: row.manufacturer.filter(|name| !name.is_empty()),
+        }
+    }
+}
+
+// A parsed upload row and the line it came from
+pub(crate) type Row = (usize, Result<CertificateData, String>);
+
+pub(crate) fn parse_rows(body: &str, format: BulkFormat) -> Result<Vec<Row>, ApiError> {
+    let rows: Vec<Row> = match format {
+        BulkFormat::Ndjson => body
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                let cert = serde_json::from_str::<CertificateData>(line).map_err(|e| e.to_string());
+                (index + 1, cert)
+            })
+            .collect(),
+        BulkFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .from_reader(body.as_bytes());
+            let headers = reader
+                .headers()
+                .map_err(|e| ApiError::bad_request(format!("Invalid CSV header: {}", e)))?
+                .clone();
+
+            reader
+                .records()
+                .map(|record| match record {
+                    Ok(record) => {
+                        let line = record.position().map_or(0, |p| p.line() as usize);
+                        let cert = record
+                            .deserialize::<CsvRow>(Some(&headers))
+                            .map(CertificateData::from)
+                            .map_err(|e| e.to_string());
+                        (line, cert)
+                    }
+                    Err(e) => {
+                        let line = e.position().map_or(0, |p| p.line() as usize);
+                        (line, Err(e.to_string()))
+                    }
+                })
+                .collect()
+        }
+    };
+
+    if rows.is_empty() {
+        return Err(ApiError::bad_request("No certificates in the upload"));
+    }
+    if rows.len() > MAX_BULK_ROWS {
+        return Err(ApiError::bad_request(format!(
+            "Too many certificates: {} (max {})",
+            rows.len(),
+            MAX_BULK_ROWS
+        )));
+    }
+
+    Ok(rows)
+}
+
+// Checks every row before anything is signed, so a bad upload is fixed and resent whole
+pub(crate) fn validate_rows(
+    state: &AppState,
+    rows: Vec<Row>,
+) -> Result<Vec<(usize, CertificateData)>, Vec<RowError>> {
+    let mut valid = Vec::with_capacity(rows.len());
+    let mut errors = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    for (line, cert) in rows {
+        let checked = cert.and_then(|cert| {
+            prepare(state, &cert).map_err(|e| e.to_string())?;
+            match seen.insert(cert.unique_id.clone(), line) {
+                Some(first) => Err(format!(
+                    "unique_id {:?} is already used on line {}",
+                    cert.unique_id, first
+                )),
+                None => Ok(cert),
+            }
+        });
+
+        match checked {
+            Ok(cert) => valid.push((line, cert)),
+            Err(message) => errors.push(RowError { line, message }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(valid)
+    } else {
+        Err(errors)
+    }
+}
+
+// Issues the rows in parallel, yielding one line per row in upload order
+pub(crate) fn issue_stream(
+    state: AppState,
+    rows: Vec<(usize, CertificateData)>,
+    qr: QrQuery,
+) -> impl Stream<Item = BulkIssueLine> + Send + 'static {
+    stream::iter(rows)
+        .map(move |(line, cert)| {
+            let state = state.clone();
+            let task = tokio::spawn(async move { issue(&state, cert, qr).await });
+
+            async move {
+                let result = task.await.unwrap_or_else(|e| Err(ApiError::internal(e)));
+                match result {
+                    Ok(certificate) => BulkIssueLine {
+                        line,
+                        certificate: Some(certificate),
+                        error: None,
+                    },
+                    Err(e) => {
+                        eprintln!("Bulk issuance failed on line {}: {}", line, e);
+                        BulkIssueLine {
+                            line,
+                            certificate: None,
+                            error: Some(ErrorResponse::from(&e)),
+                        }
+                    }
+                }
+            }
+        })
+        .buffered(SIGN_CONCURRENCY)
+}
+
+#[utoipa::path(
+    post,
+    path = "/certificates/bulk",
+    request_body(
+        content(
+            (String = "text/csv"),
+            (String = "application/x-ndjson")
+        ),
+        description = "CSV with a header row (name, unique_id, serial, date, owner, metadata, manufacturer; metadata entries separated by `|`), or one CertificateData JSON object per line"
+    ),
+    params(QrQuery),
+    responses(
+        (status = 200, description = "JSON Lines stream with one BulkIssueLine per row, in upload order", body = BulkIssueLine, content_type = "application/x-ndjson"),
+        (status = 400, description = "Unsupported content type, invalid render options, unreadable CSV header, or no / too many rows", body = ErrorResponse),
+        (status = 422, description = "Some rows are invalid, nothing was signed", body = BulkValidationFailed)
+    )
+)]
+pub async fn issue_certificates_bulk(
+    State(state): State<AppState>,
+    Query(query): Query<QrQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response, ApiError> {
+    let format = BulkFormat::from_content_type(&headers)?;
+    query.validate().map_err(ApiError::bad_request)?;
+    let rows = parse_rows(&body, format)?;
+    let total = rows.len();
+
+    let rows = match validate_rows(&state, rows) {
+        Ok(rows) => rows,
+        Err(rows) => {
+            eprintln!(
+                "Bulk issuance rejected: {} of {} rows are invalid",
+                rows.len(),
+                total
+            );
+            let body = BulkValidationFailed {
+                code: "INVALID_ROWS".to_string(),
+                message: format!(
+                    "{} of {} rows are invalid, nothing was signed",
+                    rows.len(),
+                    total
+                ),
+                rows,
+            };
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Json(body)).into_response());
+        }
+    };
+
+    println!("🧾 Issuing {} certificates", total);
+
+    let lines = issue_stream(state, rows, query).map(|line| {
+        let json = serde_json::to_string(&line).unwrap_or_default();
+        Ok::<_, Infallible>(json + "\n")
+    });
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(lines),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::AppSigner;
+    use crate::signer::registry::KeyRegistry;
+
+    const OWNER: &str = "0x70997970c51812dc3a010c7d01b50e0d17dc79c8";
+
+    fn state() -> AppState {
+        let wallet: LocalWallet =
+            "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d"
+                .parse()
+                .unwrap();
+        AppState::for_tests(KeyRegistry::with_keys([(
+            "Acme".to_string(),
+            AppSigner::Local(wallet),
+        )]))
+    }
+
+    fn lines(rows: &[Row]) -> Vec<usize> {
+        rows.iter().map(|(line, _)| *line).collect()
+    }
+
+    fn parsed(row: &Row) -> &CertificateData {
+        row.1.as_ref().unwrap()
+    }
+
+    fn json_row(unique_id: &str) -> String {
+        format!(
+            r#"{{"name":"Phone","unique_id":"{}","serial":"SN","date":1700000000,"owner":"{}","metadata":["red"]}}"#,
+            unique_id, OWNER
+        )
+    }
+
+    #[test]
+    fn csv_lines_count_the_header() {
+        let body = format!(
+            "name,unique_id,serial,date,owner,metadata\n\
+             Phone,IMEI1,SN1,1700000000,{owner},red|128GB\n\
+             Phone,IMEI2,SN2,not a date,{owner},red\n\
+             Phone,IMEI3,SN3,1700000000,{owner},blue\n",
+            owner = OWNER
+        );
+
+        let rows = parse_rows(&body, BulkFormat::Csv).unwrap();
+
+        assert_eq!(lines(&rows), vec![2, 3, 4]);
+        assert_eq!(parsed(&rows[0]).unique_id, "IMEI1");
+        assert!(rows[1].1.is_err());
+        assert_eq!(parsed(&rows[2]).unique_id, "IMEI3");
+    }
+
+    #[test]
+    fn csv_metadata_splits_on_pipes() {
+        let body = format!(
+            "name,unique_id,serial,date,owner,metadata,manufacturer\n\
+             Phone,IMEI1,SN1,1700000000,{owner},red|128GB,Acme\n\
+             Phone,IMEI2,SN2,1700000000,{owner},red,\n\
+             Phone,IMEI3,SN3,1700000000,{owner}, ,\n",
+            owner = OWNER
+        );
+
+        let rows = parse_rows(&body, BulkFormat::Csv).unwrap();
+
+        assert_eq!(parsed(&rows[0]).metadata, vec!["red", "128GB"]);
+        assert_eq!(parsed(&rows[0]).manufacturer.as_deref(), Some("Acme"));
+        assert_eq!(parsed(&rows[1]).metadata, vec!["red"]);
+        assert_eq!(parsed(&rows[1]).manufacturer, None);
+        // cells are trimmed, so a blank one is no metadata at all
+        assert!(parsed(&rows[2]).metadata.is_empty());
+    }
+
+    #[test]
+    fn ndjson_skips_blank_lines() {
+        let body = format!(
+            "\n{}\n  \n{}\nnot json\n\n",
+            json_row("IMEI1"),
+            json_row("IMEI2")
+        );
+
+        let rows = parse_rows(&body, BulkFormat::Ndjson).unwrap();
+
+        assert_eq!(lines(&rows), vec![2, 4, 5]);
+        assert_eq!(parsed(&rows[1]).unique_id, "IMEI2");
+        assert!(rows[2].1.is_err());
+    }
+
+    #[test]
+    fn empty_upload_is_rejected() {
+        assert!(parse_rows("\n\n", BulkFormat::Ndjson).is_err());
+        assert!(
+            parse_rows(
+                "name,unique_id,serial,date,owner,metadata\n",
+                BulkFormat::Csv
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn duplicate_unique_id_is_reported_on_the_second_row() {
+        let body = [json_row("IMEI1"), json_row("IMEI2"), json_row("IMEI1")].join("\n");
+        let rows = parse_rows(&body, BulkFormat::Ndjson).unwrap();
+
+        let errors = validate_rows(&state(), rows).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 3);
+        assert!(errors[0].message.contains("already used on line 1"));
+    }
+
+    #[test]
+    fn every_bad_row_is_reported() {
+        let body = format!(
+            "name,unique_id,serial,date,owner,metadata\n\
+             Phone,IMEI1,SN1,1700000000,{owner},red\n\
+             Phone,IMEI2,SN2,1700000000,{owner},\n\
+             Phone,IMEI3,SN3,1700000000,0x0000000000000000000000000000000000000001,red\n\
+             Phone,,SN4,1700000000,{owner},red\n",
+            owner = OWNER
+        );
+        let rows = parse_rows(&body, BulkFormat::Csv).unwrap();
+
+        let errors = validate_rows(&state(), rows).unwrap_err();
+
+        assert_eq!(
+            errors.iter().map(|e| e.line).collect::<Vec<_>>(),
+            vec![3, 4, 5]
+        );
+        assert!(errors[0].message.contains("Empty metadata"));
+        assert!(errors[1].message.contains("no signing key"));
+    }
+
+    #[test]
+    fn valid_rows_keep_their_lines() {
+        let body = format!("{}\n\n{}", json_row("IMEI1"), json_row("IMEI2"));
+        let rows = parse_rows(&body, BulkFormat::Ndjson).unwrap();
+
+        let valid = validate_rows(&state(), rows).unwrap();
+
+        assert_eq!(
+            valid.iter().map(|(line, _)| *line).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use crate::config::app_router::{NewOwnerClaimOwnershipCall, Ownership};
+use crate::config::app_state::AppState;
+use crate::error::{ApiError, ErrorResponse};
+use crate::indexer::store::StoredEvent;
+use crate::models::events::{ItemCreated, OwnershipCode};
+use crate::models::ownership_model::{ItemHistory, ProvenanceEvent, ProvenanceKind};
+use crate::utility::item_hash;
+use axum::{Json, extract::Path, extract::State};
+use ethers::abi::AbiDecode;
+use ethers::prelude::*;
+use ethers::utils::keccak256;
+
+fn provenance(
+    kind: ProvenanceKind,
+    owner: Address,
+    counterparty: Option<Address>,
+    item_hash: Option<H256>,
+    event: &StoredEvent,
+) -> ProvenanceEvent {
+    ProvenanceEvent {
+        kind,
+        owner,
+        counterparty,
+        item_hash,
+        block_number: event.block_number,
+        timestamp: event.timestamp,
+        tx_hash: event.tx_hash,
+    }
+}
+
+// OwnershipClaimed only names the two owners, so the claim of `code` is the first
+// (temp owner, owner) claim after it whose transaction spent this item hash
+async fn find_claim(
+    state: &AppState,
+    code: &StoredEvent,
+    until: Option<(u64, u64)>,
+    owner: Address,
+    temp_owner: Address,
+    hash: H256,
+) -> Result<Option<StoredEvent>, ApiError> {
+    let candidates = state
+        .event_store()?
+        .find_events("OwnershipClaimed", 1, H256::from(temp_owner))?
+        .into_iter()
+        .filter(|claim| claim.topics.get(1) == Some(&H256::from(owner)))
+        .filter(|claim| claim.position() > code.position())
+        .filter(|claim| until.is_none_or(|until| claim.position() < until));
+
+    for claim in candidates {
+        let tx = state
+            .eth_client
+            .get_transaction(claim.tx_hash)
+            .await
+            .map_err(|e| ApiError::Rpc(e.to_string()))?;
+        let Some(tx) = tx else {
+            return Ok(Some(claim));
+        };
+        match NewOwnerClaimOwnershipCall::decode(&tx.input) {
+            Ok(call) if H256::from(call.item_hash) == hash => return Ok(Some(claim)),
+            Ok(_) => continue,
+            // claimed through another contract, the owners are all we can match on
+            Err(_) => return Ok(Some(claim)),
+        }
+    }
+
+    Ok(None)
+}
+
+#[utoipa::path(
+    get,
+    path = "/items/{item_id}/history",
+    params(
+        ("item_id" = String, Path, description = "Unique id of the item")
+    ),
+    responses(
+        (status = 200, description = "Chain of custody of the item, oldest first", body = ItemHistory),
+        (status = 404, description = "Item does not exist, is not indexed yet, or the indexer is disabled", body = ErrorResponse),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
+    )
+)]
+pub async fn item_history(
+    State(state): State<AppState>,
+    Path(item_id): Path<String>,
+) -> Result<Json<ItemHistory>, ApiError> {
+    let contract = Ownership::new(state.ownership_contract, state.eth_client.clone());
+
+    // everything but the owner is fixed at creation, so this gives us every past item hash too
+    let mut item = contract.get_item(item_id.clone()).call().await?;
+    let current_owner = item.owner;
+    let indexed_to_block = state.event_store()?.checkpoint()?.map(|c| c.number);
+
+    // indexed strings are logged as their hash
+    let created = state
+        .event_store()?
+        .find_events("ItemCreated", 1, H256::from(keccak256(item_id.as_bytes())))?
+        .into_iter()
+        .filter_map(|event| {
+            event
+                .decode::<ItemCreated>()
+                .map(|decoded| (event, decoded))
+        })
+        .next_back()
+        .ok_or_else(|| {
+            ApiError::NotFound(format!(
+                "no ItemCreated event indexed for {:?} (indexed up to block {})",
+                item_id,
+                indexed_to_block.map_or("none".to_string(), |n| n.to_string())
+            ))
+        })?;
+
+    let (created, ItemCreated { owner, .. }) = created;
+    let mut events = vec![provenance(
+        ProvenanceKind::Created,
+        owner,
+        None,
+        None,
+        &created,
+    )];
+    let mut owner = owner;
+    let mut position = created.position();
+
+    // follow the codes issued for each successive owner until nobody claimed one
+    'owners: loop {
+        item.owner = owner;
+        let hash = item_hash(&item);
+
+        let mut hash_events = state.event_store()?.find_events("OwnershipCode", 1, hash)?;
+        hash_events.extend(state.event_store()?.find_events("CodeRevoked", 1, hash)?);
+        hash_events.retain(|event| event.position() > position);
+        hash_events.sort_by_key(StoredEvent::position);
+
+        let mut pending = None;
+        for (i, event) in hash_events.iter().enumerate() {
+            if event.name == "CodeRevoked" {
+                events.push(provenance(
+                    ProvenanceKind::CodeRevoked,
+                    owner,
+                    pending.take(),
+                    Some(hash),
+                    event,
+                ));
+                continue;
+            }
+
+            let Some(code) = event.decode::<OwnershipCode>() else {
+                continue;
+            };
+            pending = Some(code.temp_owner);
+            events.push(provenance(
+                ProvenanceKind::CodeIssued,
+                owner,
+                Some(code.temp_owner),
+                Some(hash),
+                event,
+            ));
+
+            let until = hash_events.get(i + 1).map(StoredEvent::position);
+            if let Some(claim) =
+                find_claim(&state, event, until, owner, code.temp_owner, hash).await?
+            {
+                events.push(provenance(
+                    ProvenanceKind::Claimed,
+                    code.temp_owner,
+                    Some(owner),
+                    Some(hash),
+                    &claim,
+                ));
+                owner = code.temp_owner;
+                position = claim.position();
+                continue 'owners;
+            }
+        }
+
+        break;
+    }
+
+    Ok(Json(ItemHistory {
+        item_id,
+        current_owner,
+        indexed_to_block,
+        events,
+    }))
+}
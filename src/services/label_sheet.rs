@@ -0,0 +1,407 @@
+use crate::config::app_state::AppState;
+use crate::error::{ApiError, ErrorResponse};
+use crate::models::certificate_model::{
+    BulkValidationFailed, LabelField, LabelSheetInput, LabelTemplate, QrQuery, RowError,
+    SignedCertificate,
+};
+use crate::services::qr_code::{Modules, deflate, draw_qr_pdf, qr_modules};
+use crate::services::verify_authenticity::{lookup_manufacturer, recover_signer};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::{Json, extract::State};
+use ethers::prelude::*;
+use pdf_writer::{Content, Filter, Name, Pdf, Rect, Ref, Str};
+use rayon::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+use validator::Validate;
+
+// Largest sheet accepted in one request
+pub(crate) const MAX_LABELS: usize = 10_000;
+// labels smaller than this can't hold a QR code a phone will read
+const MIN_LABEL_MM: f32 = 10.0;
+// blank space inside each label, around the QR code and the text
+const LABEL_PADDING_MM: f32 = 1.5;
+
+fn mm(value: f32) -> f32 {
+    value * 72.0 / 25.4
+}
+
+// Helvetica advance widths for ' ' to '~', in thousandths of the font size
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+// WinAnsi bytes for `text`, characters the standard fonts can't show become '?'
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c as u32 {
+            code @ (0x20..=0x7e | 0xa0..=0xff) => code as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+fn text_width(bytes: &[u8], font_size: f32) -> f32 {
+    let units: u32 = bytes
+        .iter()
+        .map(|&b| match b {
+            0x20..=0x7e => u32::from(HELVETICA_WIDTHS[usize::from(b - 0x20)]),
+            _ => 556,
+        })
+        .sum();
+    units as f32 * font_size / 1000.0
+}
+
+// `text` cut short with "..." so it fits in `max_width` points
+fn fit(text: &str, max_width: f32, font_size: f32) -> Vec<u8> {
+    let mut bytes = win_ansi(text);
+    if text_width(&bytes, font_size) <= max_width {
+        return bytes;
+    }
+    while !bytes.is_empty()
+        && text_width(&bytes, font_size) + text_width(b"...", font_size) > max_width
+    {
+        bytes.pop();
+    }
+    bytes.extend_from_slice(b"...");
+    bytes
+}
+
+// Unix seconds as YYYY-MM-DD (proleptic Gregorian, UTC)
+fn format_date(secs: u64) -> String {
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn field_text(field: LabelField, cert: &SignedCertificate, manufacturer: &str) -> String {
+    match field {
+        LabelField::Name => cert.name.clone(),
+        LabelField::UniqueId => format!("ID: {}", cert.unique_id),
+        LabelField::Serial => format!("S/N: {}", cert.serial),
+        LabelField::Date => format!("Date: {}", format_date(cert.date)),
+        LabelField::Owner => format!("Owner: {}", cert.owner),
+        LabelField::Manufacturer => format!("Made by {}", manufacturer),
+        LabelField::Metadata => cert.metadata.join(", "),
+    }
+}
+
+fn qr_options(template: &LabelTemplate) -> QrQuery {
+    QrQuery {
+        qr_payload: template.qr_payload,
+        ec_level: template.ec_level,
+        quiet_zone: template.quiet_zone,
+        ..QrQuery::default()
+    }
+}
+
+// Page size and label cell size in points
+struct Layout {
+    page_width: f32,
+    page_height: f32,
+    label_width: f32,
+    label_height: f32,
+}
+
+impl Layout {
+    fn new(template: &LabelTemplate) -> Result<Self, ApiError> {
+        let (width_mm, height_mm) = match (template.page_width_mm, template.page_height_mm) {
+            (Some(width), Some(height)) => (width, height),
+            (None, None) => template.page_size.dimensions_mm(),
+            _ => {
+                return Err(ApiError::bad_request(
+                    "Set both page_width_mm and page_height_mm, or neither",
+                ));
+            }
+        };
+
+        let columns = template.columns as f32;
+        let rows = template.rows as f32;
+        let label_width_mm =
+            (width_mm - 2.0 * template.margin_mm - (columns - 1.0) * template.gap_mm) / columns;
+        let label_height_mm =
+            (height_mm - 2.0 * template.margin_mm - (rows - 1.0) * template.gap_mm) / rows;
+        if label_width_mm < MIN_LABEL_MM || label_height_mm < MIN_LABEL_MM {
+            return Err(ApiError::bad_request(format!(
+                "Labels would be {:.1} x {:.1} mm, at least {} mm each way is needed",
+                label_width_mm, label_height_mm, MIN_LABEL_MM
+            )));
+        }
+
+        Ok(Layout {
+            page_width: mm(width_mm),
+            page_height: mm(height_mm),
+            label_width: mm(label_width_mm),
+            label_height: mm(label_height_mm),
+        })
+    }
+}
+
+// Draws one label with its bottom left corner at (x, y)
+fn draw_label(
+    content: &mut Content,
+    layout: &Layout,
+    template: &LabelTemplate,
+    options: &QrQuery,
+    (x, y): (f32, f32),
+    modules: &Modules,
+    lines: &[String],
+) {
+    let padding = mm(LABEL_PADDING_MM);
+    let inner_width = layout.label_width - 2.0 * padding;
+    let inner_height = layout.label_height - 2.0 * padding;
+
+    if template.outline {
+        content
+            .set_stroke_gray(0.6)
+            .set_line_width(0.25)
+            .rect(x, y, layout.label_width, layout.label_height)
+            .stroke();
+    }
+
+    // the code takes the full height, and at most half the width when there is text
+    let qr_side = if lines.is_empty() {
+        inner_width.min(inner_height)
+    } else {
+        inner_height.min(inner_width / 2.0)
+    };
+    let module = qr_side / modules.side as f32;
+    draw_qr_pdf(
+        content,
+        modules,
+        x + padding,
+        y + padding + (inner_height - qr_side) / 2.0,
+        module,
+        options,
+    );
+
+    let font_size = template.font_size_pt;
+    let leading = font_size * 1.2;
+    let text_x = x + 2.0 * padding + qr_side;
+    let text_room = x + layout.label_width - padding - text_x;
+    let fitting = ((inner_height / leading) as usize).min(lines.len());
+    if fitting == 0 || text_room <= 0.0 {
+        return;
+    }
+
+    // the block of lines is centred next to the code
+    let block = fitting as f32 * leading;
+    let first_baseline = y + padding + (inner_height + block) / 2.0 - font_size;
+    content
+        .set_fill_gray(0.0)
+        .begin_text()
+        .set_font(Name(b"F1"), font_size);
+    for (index, line) in lines[..fitting].iter().enumerate() {
+        let baseline = first_baseline - index as f32 * leading;
+        content
+            .set_text_matrix([1.0, 0.0, 0.0, 1.0, text_x, baseline])
+            .show(Str(&fit(line, text_room, font_size)));
+    }
+    content.end_text();
+}
+
+fn render_sheet(
+    template: &LabelTemplate,
+    layout: &Layout,
+    labels: &[(Modules, Vec<String>)],
+) -> Result<Vec<u8>, ApiError> {
+    let options = qr_options(template);
+    let per_page = (template.columns * template.rows) as usize;
+    let pages = labels.len().div_ceil(per_page);
+
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let font_id = Ref::new(3);
+    // each page takes two ids, the page and its content stream
+    let page_ids: Vec<Ref> = (0..pages).map(|i| Ref::new(4 + 2 * i as i32)).collect();
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id)
+        .kids(page_ids.iter().copied())
+        .count(pages as i32);
+    // a standard font, every PDF reader has it so nothing is embedded
+    pdf.type1_font(font_id)
+        .base_font(Name(b"Helvetica"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+
+    let margin = mm(template.margin_mm);
+    let gap = mm(template.gap_mm);
+    for (page_labels, &page_id) in labels.chunks(per_page).zip(&page_ids) {
+        let content_id = Ref::new(page_id.get() + 1);
+        // the page is written out when its writer is dropped
+        {
+            let mut page = pdf.page(page_id);
+            page.media_box(Rect::new(0.0, 0.0, layout.page_width, layout.page_height))
+                .parent(page_tree_id)
+                .contents(content_id);
+            page.resources().fonts().pair(Name(b"F1"), font_id);
+        }
+
+        let mut content = Content::new();
+        for (index, (modules, lines)) in page_labels.iter().enumerate() {
+            let column = index % template.columns as usize;
+            let row = index / template.columns as usize;
+            // rows are counted from the top of the page, PDF y from the bottom
+            let x = margin + column as f32 * (layout.label_width + gap);
+            let y = layout.page_height
+                - margin
+                - (row + 1) as f32 * layout.label_height
+                - row as f32 * gap;
+            draw_label(
+                &mut content,
+                layout,
+                template,
+                &options,
+                (x, y),
+                modules,
+                lines,
+            );
+        }
+        pdf.stream(content_id, &deflate(&content.finish())?)
+            .filter(Filter::FlateDecode);
+    }
+
+    Ok(pdf.finish())
+}
+
+#[utoipa::path(
+    post,
+    path = "/label_sheets",
+    request_body = LabelSheetInput,
+    responses(
+        (status = 200, description = "Multi-page PDF with one label per certificate", content_type = "application/pdf", body = Vec<u8>),
+        (status = 400, description = "No / too many certificates, or a template the labels don't fit", body = ErrorResponse),
+        (status = 422, description = "Some certificates don't verify or don't fit a QR code, nothing was printed", body = BulkValidationFailed),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
+    )
+)]
+pub async fn generate_label_sheet(
+    State(state): State<AppState>,
+    Json(input): Json<LabelSheetInput>,
+) -> Result<Response, ApiError> {
+    let LabelSheetInput {
+        certificates,
+        template,
+    } = input;
+    if certificates.is_empty() || certificates.len() > MAX_LABELS {
+        return Err(ApiError::bad_request(format!(
+            "A label sheet holds 1 to {} certificates, got {}",
+            MAX_LABELS,
+            certificates.len()
+        )));
+    }
+    template.validate().map_err(ApiError::bad_request)?;
+    let layout = Layout::new(&template)?;
+
+    // only certificates that verify get a label, so nothing printed is rejected on scanning
+    let domain = state.eip712_domain();
+    let (certificates, recovered) = tokio::task::spawn_blocking(move || {
+        let recovered: Vec<_> = certificates
+            .par_iter()
+            .map(|cert| recover_signer(cert, domain.clone()).map(|(_, signer)| signer))
+            .collect();
+        (certificates, recovered)
+    })
+    .await
+    .map_err(ApiError::internal)?;
+
+    let signers: BTreeSet<Address> = recovered
+        .iter()
+        .filter_map(|r| r.as_ref().ok())
+        .copied()
+        .collect();
+    let mut manufacturers = HashMap::new();
+    for signer in signers {
+        manufacturers.insert(signer, lookup_manufacturer(&state, signer).await?);
+    }
+
+    let total = certificates.len();
+    let verify_url = state.config.server.verify_url.clone();
+    let rows = tokio::task::spawn_blocking(move || {
+        let options = qr_options(&template);
+
+        let checked: Vec<Result<(Modules, Vec<String>), RowError>> = certificates
+            .par_iter()
+            .zip(recovered)
+            .enumerate()
+            .map(|(index, (cert, recovered))| {
+                // `line` is the certificate's position in the request, counted from 1
+                let row_error = |message: String| RowError {
+                    line: index + 1,
+                    message,
+                };
+                let signer = recovered.map_err(|(reason, _)| row_error(format!("{:?}", reason)))?;
+                let manufacturer = manufacturers[&signer].as_deref().ok_or_else(|| {
+                    row_error(format!(
+                        "signer {:?} is not a registered manufacturer",
+                        signer
+                    ))
+                })?;
+                let modules = qr_modules(cert, &options, verify_url.as_deref())
+                    .map_err(|e| row_error(e.to_string()))?;
+                let lines = template
+                    .fields
+                    .iter()
+                    .map(|&field| field_text(field, cert, manufacturer))
+                    .collect();
+                Ok((modules, lines))
+            })
+            .collect();
+
+        let errors: Vec<RowError> = checked
+            .iter()
+            .filter_map(|r| r.as_ref().err())
+            .cloned()
+            .collect();
+        if !errors.is_empty() {
+            return Ok(Err(errors));
+        }
+        let labels: Vec<_> = checked.into_iter().filter_map(Result::ok).collect();
+        render_sheet(&template, &layout, &labels).map(Ok)
+    })
+    .await
+    .map_err(ApiError::internal)??;
+
+    let pdf = match rows {
+        Ok(pdf) => pdf,
+        Err(rows) => {
+            let body = BulkValidationFailed {
+                code: "INVALID_ROWS".to_string(),
+                message: format!(
+                    "{} of {} certificates can't be printed, nothing was rendered",
+                    rows.len(),
+                    total
+                ),
+                rows,
+            };
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Json(body)).into_response());
+        }
+    };
+
+    println!("🏷️ Rendered a label sheet of {} certificates", total);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"labels.pdf\"",
+            ),
+        ],
+        pdf,
+    )
+        .into_response())
+}
@@ -1,4 +1,13 @@
 pub(crate) mod other_tests;
 pub(crate) mod verify_authenticity;
 pub(crate) mod create_eip712;
-pub(crate) mod qr_code;
\ No newline at end of file
+pub(crate) mod qr_code;
+pub(crate) mod verify_qr;
+pub(crate) mod label_sheet;
+pub(crate) mod ownership;
+pub(crate) mod change_ownership;
+pub(crate) mod item_history;
+pub(crate) mod registry_snapshot;
+pub(crate) mod issue_certificate;
+pub(crate) mod register_manufacturer;
+pub(crate) mod certificate_batch;
@@ -1,13 +1,13 @@
 use crate::models::certificate_model::RegInput;
 use crate::models::certificate_model::{Certificate, CertificateData};
-use crate::models::events::ManufacturerRegistered;
-use crate::config::app_router::{Authenticity, authenticity};
+use crate::config::app_router::{Authenticity, EriErrorsErrors, authenticity, eri_errors};
 use crate::config::app_state::AppState;
-use axum::{Json, extract::Path, extract::State, http::StatusCode};
-use ethabi::RawLog;
-use ethers::types::transaction::eip712::Eip712;
-use ethers::{contract::EthEvent, prelude::*, signers::Signer, types::Signature};
-use std::error::Error;
+use crate::error::{ApiError, ErrorResponse};
+use crate::services::issue_certificate::sign_certificate_data;
+use crate::services::register_manufacturer::register_manufacturer;
+use crate::services::verify_authenticity::lookup_manufacturer;
+use axum::{Json, extract::Path, extract::State};
+use ethers::{prelude::*, signers::Signer, types::Signature};
 
 //============== FOR TEST ONLY => WILL BE REMOVED WHEN DONE =======================
 
@@ -17,59 +17,20 @@ use std::error::Error;
     request_body = RegInput,
     responses(
         (status = 200, description = "Signature verification result", body = String),
-        (status = 400, description = "Invalid input"),
-        (status = 500, description = "Internal server error")
+        (status = 400, description = "Invalid input", body = ErrorResponse),
+        (status = 409, description = "Manufacturer or name already registered", body = ErrorResponse),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
     )
 )]
 pub async fn manufacturer_registers(
     State(state): State<AppState>,
     Json(input): Json<RegInput>,
-) -> Result<Json<String>, StatusCode> {
-    // Fetch the contract's owner
-    let contract = Authenticity::new(state.authenticity_contract, state.eth_client.clone());
-
-    let receipt = contract
-        .manufacturer_registers(input.name)
-        .send()
-        .await
-        .map_err(|e| {
-            eprintln!("Transaction send error: {:?}", e.to_string());
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .await
-        .map_err(|e| {
-            eprintln!("Transaction confirmation error: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    if receipt.status != Some(1.into()) {
-        return Err(StatusCode::BAD_REQUEST);
-    }
-
-    let mut event_res = ManufacturerRegistered::init();
-
-    for log in receipt.logs.iter() {
-        let raw_log = RawLog {
-            topics: log.topics.clone(),
-            data: log.data.clone().to_vec(),
-        };
-
-        if let Ok(event) = <ManufacturerRegistered as EthEvent>::decode_log(&raw_log) {
-            event_res = ManufacturerRegistered::new(
-                event.manufacturer_address,
-                event.manufacturer_name.clone(),
-            );
-
-            println!("📦 Manufacturer Registers:");
-            println!("    Manufacturer Address: {}", event.manufacturer_address);
-            println!("    Manufacturer Name: {:?}", event.manufacturer_name);
-        }
-    }
+) -> Result<Json<String>, ApiError> {
+    let (address, name) = register_manufacturer(&state, input.name).await?;
 
     Ok(Json(format!(
         "Manufacturer Address: {:?}, Manufacturer Name: {:?}",
-        event_res.manufacturer_address, event_res.manufacturer_name
+        address, name
     )))
 }
 
@@ -81,27 +42,24 @@ pub async fn manufacturer_registers(
     ),
     responses(
         (status = 200, description = "Owner retrieved successfully", body = String),
-        (status = 400, description = "Invalid Owner Address"),
-        (status = 500, description = "Internal server error")
+        (status = 400, description = "Invalid Owner Address", body = ErrorResponse),
+        (status = 404, description = "Manufacturer not registered", body = ErrorResponse),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
     )
 )]
 pub async fn get_owner(
     State(state): State<AppState>,
     Path(input): Path<String>,
-) -> Result<Json<Address>, StatusCode> {
-    let contract = Authenticity::new(state.authenticity_contract, state.eth_client.clone());
-
-    let owner = input.parse().unwrap();
-    let manufacturer_address = contract
-        .get_manufacturer_address(owner)
-        .call()
-        .await
-        .map_err(|e| {
-            eprintln!("Contract call error: {:?}", e.to_string());
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    Ok(Json(manufacturer_address))
+) -> Result<Json<Address>, ApiError> {
+    let owner: Address = input
+        .parse()
+        .map_err(|_| ApiError::bad_request(format!("Invalid owner address: {}", input)))?;
+
+    // same answer getManufacturerAddress gives, served from the manufacturer cache
+    match lookup_manufacturer(&state, owner).await? {
+        Some(_) => Ok(Json(owner)),
+        None => Err(ApiError::Contract(EriErrorsErrors::DOES_NOT_EXIST(eri_errors::DOES_NOT_EXIST))),
+    }
 }
 
 #[utoipa::path( //TODO: This will be called from the frontend, just created this for test
@@ -110,31 +68,31 @@ pub async fn get_owner(
     request_body = CertificateData,
     responses(
         (status = 200, description = "Signature verified on-chain successfully", body = String),
-        (status = 400, description = "Invalid signature"),
-        (status = 500, description = "Internal server error")
+        (status = 400, description = "Invalid input", body = ErrorResponse),
+        (status = 403, description = "No signing key for this manufacturer", body = ErrorResponse),
+        (status = 422, description = "Invalid signature", body = ErrorResponse),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
     )
 )]
 pub async fn verify_signature(
     State(state): State<AppState>,
     Json(cert): Json<CertificateData>,
-) -> anyhow::Result<Json<String>, StatusCode> {
+) -> Result<Json<String>, ApiError> {
 
-    let certificate: Certificate = cert
-        .clone()
-        .try_into()
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let certificate: Certificate = Certificate::try_from(cert.clone())
+        .map_err(ApiError::bad_request)?
+        .with_domain(state.eip712_domain());
 
     // accessing the wallet from SignerMiddleware
     // Sign the certificate
-    let signature: Signature = state
-        .eth_client
-        .signer()
-        .sign_typed_data(&certificate)
+    // each manufacturer signs with its own key, never with another's
+    let (_, signer) = state
+        .keys
+        .signer_for(cert.manufacturer.as_deref(), certificate.owner)?;
+    let signature: Signature = signer
+        .sign_certificate(&certificate)
         .await
-        .map_err(|e| {
-            eprintln!("Signature error: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        .map_err(|e| ApiError::internal(format!("Signature error: {:?}", e)))?;
 
     eprintln!("Signature: {:?}", signature);
 
@@ -145,7 +103,7 @@ pub async fn verify_signature(
     // Call create_item
     let contract = Authenticity::new(state.authenticity_contract, state.eth_client.clone());
 
-    eprintln!("Address: {:?}", state.eth_client.signer().address());
+    eprintln!("Address: {:?}", signer.address());
     let bytes_sign = Bytes::from(signature.to_vec());
 
     eprintln!("Bytes Signature: {:?}", bytes_sign);
@@ -154,11 +112,7 @@ pub async fn verify_signature(
     let result = contract
         .verify_signature(contract_cert, bytes_sign)
         .call()
-        .await
-        .map_err(|e| {
-            eprintln!("Transaction send error: {:?}", e.to_string());
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        .await?;
 
     eprintln!("Result: {:?}", result);
 
@@ -171,28 +125,16 @@ pub async fn verify_signature(
     request_body = CertificateData,
     responses(
         (status = 200, description = "Signature verification result", body = String),
-        (status = 400, description = "Invalid input"),
-        (status = 500, description = "Internal server error")
+        (status = 400, description = "Invalid input", body = ErrorResponse),
+        (status = 403, description = "No signing key for this manufacturer", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
     )
 )]
 pub async fn generate_signature(
     State(state): State<AppState>,
     Json(cert): Json<CertificateData>,
-) -> Result<Json<String>, StatusCode> {
-    let certificate: Certificate = cert
-        .clone()
-        .try_into()
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
-
-    let signature: Signature = state
-        .eth_client
-        .signer()
-        .sign_typed_data(&certificate)
-        .await
-        .map_err(|e| {
-            eprintln!("Signature error: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+) -> Result<Json<String>, ApiError> {
+    let (signed, _, _) = sign_certificate_data(&state, cert).await?;
 
-    Ok(Json("0x".to_owned() + &*signature.to_string()))
+    Ok(Json(signed.signature))
 }
@@ -0,0 +1,191 @@
+use crate::config::app_router::Ownership;
+use crate::config::app_state::AppState;
+use crate::error::{ApiError, ErrorResponse};
+use crate::models::ownership_model::{Item, Owner, UserProfile, UserRegInput};
+use crate::utility::parse_item_hash;
+use axum::{Json, extract::Path, extract::State};
+use ethers::types::Address;
+
+fn parse_address(input: &str) -> Result<Address, ApiError> {
+    input
+        .parse()
+        .map_err(|e| ApiError::bad_request(format!("Invalid address {:?}: {:?}", input, e)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/user_registers",
+    request_body = UserRegInput,
+    responses(
+        (status = 200, description = "User registered successfully", body = String),
+        (status = 400, description = "Invalid input", body = ErrorResponse),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
+    )
+)]
+pub async fn user_registers(
+    State(state): State<AppState>,
+    Json(input): Json<UserRegInput>,
+) -> Result<Json<String>, ApiError> {
+    let contract = Ownership::new(state.ownership_contract, state.eth_client.clone());
+
+    let receipt = contract
+        .user_registers(input.username)
+        .send()
+        .await?
+        .await?
+        .ok_or_else(|| ApiError::Rpc("transaction dropped from the mempool".to_string()))?;
+
+    if receipt.status != Some(1.into()) {
+        return Err(ApiError::Reverted(format!(
+            "transaction {:?} reverted",
+            receipt.transaction_hash
+        )));
+    }
+
+    Ok(Json(format!("{:?}", receipt.transaction_hash)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/get_user/{address}",
+    params(
+        ("address" = String, Path, description = "Wallet address of the user")
+    ),
+    responses(
+        (status = 200, description = "User retrieved successfully", body = UserProfile),
+        (status = 400, description = "Invalid user address", body = ErrorResponse),
+        (status = 404, description = "User does not exist", body = ErrorResponse),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
+    )
+)]
+pub async fn get_user(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+) -> Result<Json<UserProfile>, ApiError> {
+    let contract = Ownership::new(state.ownership_contract, state.eth_client.clone());
+
+    let user = contract.get_user(parse_address(&address)?).call().await?;
+
+    Ok(Json(user.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/get_all_my_items",
+    responses(
+        (status = 200, description = "Items owned by the server wallet", body = Vec<Item>),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
+    )
+)]
+pub async fn get_all_my_items(State(state): State<AppState>) -> Result<Json<Vec<Item>>, ApiError> {
+    let contract = Ownership::new(state.ownership_contract, state.eth_client.clone());
+
+    // getAllMyItems reads msg.sender, which is the wallet behind eth_client
+    let items = contract.get_all_my_items().call().await?;
+
+    let items = items
+        .into_iter()
+        .map(Item::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Json(items))
+}
+
+#[utoipa::path(
+    get,
+    path = "/get_item/{item_id}",
+    params(
+        ("item_id" = String, Path, description = "Unique id of the item")
+    ),
+    responses(
+        (status = 200, description = "Item retrieved successfully", body = Item),
+        (status = 404, description = "Item does not exist", body = ErrorResponse),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
+    )
+)]
+pub async fn get_item(
+    State(state): State<AppState>,
+    Path(item_id): Path<String>,
+) -> Result<Json<Item>, ApiError> {
+    let contract = Ownership::new(state.ownership_contract, state.eth_client.clone());
+
+    let item = contract.get_item(item_id).call().await?;
+
+    Ok(Json(item.try_into()?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/verify_ownership/{item_id}",
+    params(
+        ("item_id" = String, Path, description = "Unique id of the item")
+    ),
+    responses(
+        (status = 200, description = "Current owner of the item", body = Owner),
+        (status = 404, description = "Item does not exist", body = ErrorResponse),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
+    )
+)]
+pub async fn verify_ownership(
+    State(state): State<AppState>,
+    Path(item_id): Path<String>,
+) -> Result<Json<Owner>, ApiError> {
+    let contract = Ownership::new(state.ownership_contract, state.eth_client.clone());
+
+    let owner = contract.verify_ownership(item_id).call().await?;
+
+    Ok(Json(owner.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/is_owner/{address}/{item_id}",
+    params(
+        ("address" = String, Path, description = "Address to check"),
+        ("item_id" = String, Path, description = "Unique id of the item")
+    ),
+    responses(
+        (status = 200, description = "Whether the address owns the item", body = bool),
+        (status = 400, description = "Invalid address", body = ErrorResponse),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
+    )
+)]
+pub async fn is_owner(
+    State(state): State<AppState>,
+    Path((address, item_id)): Path<(String, String)>,
+) -> Result<Json<bool>, ApiError> {
+    let contract = Ownership::new(state.ownership_contract, state.eth_client.clone());
+
+    let result = contract
+        .is_owner(parse_address(&address)?, item_id)
+        .call()
+        .await?;
+
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/get_temp_owner/{item_hash}",
+    params(
+        ("item_hash" = String, Path, description = "Change of ownership code (bytes32 hex)")
+    ),
+    responses(
+        (status = 200, description = "Pending owner for the ownership code", body = String),
+        (status = 400, description = "Invalid item hash", body = ErrorResponse),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
+    )
+)]
+pub async fn get_temp_owner(
+    State(state): State<AppState>,
+    Path(item_hash): Path<String>,
+) -> Result<Json<Address>, ApiError> {
+    let contract = Ownership::new(state.ownership_contract, state.eth_client.clone());
+
+    let temp_owner = contract
+        .get_temp_owner(parse_item_hash(&item_hash)?.into())
+        .call()
+        .await?;
+
+    Ok(Json(temp_owner))
+}
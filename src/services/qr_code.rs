@@ -1,48 +1,435 @@
-use axum::http::StatusCode;
 use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use eri_core::{decode_qr_payload, encode_qr_payload, encode_qr_url_payload, parse_signature};
+use pdf_writer::{Content, Filter, Pdf, Rect, Ref};
+use qrcode::types::{Color, QrError};
 use qrcode::{EcLevel, QrCode};
-use qrcode::render::svg;
+use std::io::Write;
+use tokio::sync::Semaphore;
 use validator::Validate;
-use crate::models::certificate_model::SignedCertificate;
+use crate::config::app_state::AppState;
+use crate::error::{ApiError, ErrorResponse};
+use crate::models::certificate_model::{
+    Certificate, QrColor, QrEcLevel, QrFormat, QrPayload, QrPayloadInput, QrQuery,
+    SignedCertificate,
+};
+
+// The text a QR code for `cert` holds, `verify_url` is the configured link host
+fn qr_payload(
+    cert: &SignedCertificate,
+    payload: QrPayload,
+    verify_url: Option<&str>,
+) -> Result<String, ApiError> {
+    match payload {
+        QrPayload::Json => {
+            let cert_str = serde_json::to_string(cert).map_err(ApiError::internal)?;
+
+            // check QR code size limit
+            if cert_str.len() > 2953 {
+                return Err(ApiError::bad_request("Certificate data too large for QR code"));
+            }
+            Ok(cert_str)
+        }
+        QrPayload::Compact => {
+            let certificate = Certificate::try_from(cert.clone()).map_err(ApiError::bad_request)?;
+            let signature = parse_signature(&cert.signature).map_err(ApiError::bad_request)?;
+            encode_qr_payload(&certificate, &signature).map_err(ApiError::bad_request)
+        }
+        QrPayload::Url => {
+            let verify_url = verify_url.ok_or_else(|| {
+                ApiError::bad_request("qr_payload=url needs `server.verify_url` (VERIFY_URL) configured")
+            })?;
+            let certificate = Certificate::try_from(cert.clone()).map_err(ApiError::bad_request)?;
+            let signature = parse_signature(&cert.signature).map_err(ApiError::bad_request)?;
+            let token =
+                encode_qr_url_payload(&certificate, &signature).map_err(ApiError::bad_request)?;
+            Ok(format!("{}/v/{}", verify_url, token))
+        }
+    }
+}
+
+// A rendered QR code, served with its own Content-Type
+pub(crate) struct RenderedQr {
+    pub format: QrFormat,
+    pub bytes: Vec<u8>,
+}
+
+impl RenderedQr {
+    // For embedding in JSON: SVG and text as is, images and PDFs as base64
+    pub fn into_text(self) -> String {
+        if self.format.is_binary() {
+            BASE64.encode(&self.bytes)
+        } else {
+            String::from_utf8(self.bytes).unwrap_or_default()
+        }
+    }
+}
+
+impl IntoResponse for RenderedQr {
+    fn into_response(self) -> Response {
+        ([(header::CONTENT_TYPE, self.format.content_type())], self.bytes).into_response()
+    }
+}
+
+// The module grid with the quiet zone around it, `true` for dark
+pub(crate) struct Modules {
+    pub side: usize,
+    dark: Vec<bool>,
+}
+
+impl Modules {
+    fn new(qr_code: &QrCode, quiet_zone: usize) -> Self {
+        let width = qr_code.width();
+        let side = width + 2 * quiet_zone;
+        let mut dark = vec![false; side * side];
+        for (i, color) in qr_code.to_colors().into_iter().enumerate() {
+            let (x, y) = (i % width + quiet_zone, i / width + quiet_zone);
+            dark[y * side + x] = color == Color::Dark;
+        }
+        Modules { side, dark }
+    }
+
+    fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.dark[y * self.side + x]
+    }
+
+    // (x, length) of every horizontal run of dark modules on row `y`
+    fn dark_runs(&self, y: usize) -> Vec<(usize, usize)> {
+        let mut runs = Vec::new();
+        let mut x = 0;
+        while x < self.side {
+            if self.is_dark(x, y) {
+                let start = x;
+                while x < self.side && self.is_dark(x, y) {
+                    x += 1;
+                }
+                runs.push((start, x - start));
+            } else {
+                x += 1;
+            }
+        }
+        runs
+    }
+
+    // whole pixels per module, enough for the code to be at least `size` wide
+    fn module_pixels(&self, size: u32) -> usize {
+        (size as usize).div_ceil(self.side).max(1)
+    }
+
+    fn rgb(&self, module: usize, dark: QrColor, light: QrColor) -> (Vec<u8>, usize) {
+        let side = self.side * module;
+        let mut pixels = Vec::with_capacity(side * side * 3);
+        for y in 0..side {
+            for x in 0..side {
+                let color = if self.is_dark(x / module, y / module) { dark } else { light };
+                pixels.extend_from_slice(&color.0);
+            }
+        }
+        (pixels, side)
+    }
+}
+
+fn render_svg(modules: &Modules, options: &QrQuery) -> String {
+    let pixels = modules.side * modules.module_pixels(options.size);
+    let mut path = String::new();
+    for y in 0..modules.side {
+        for (x, length) in modules.dark_runs(y) {
+            path.push_str(&format!("M{} {}h{}v1h-{}z", x, y, length, length));
+        }
+    }
+
+    format!(
+        concat!(
+            r#"<?xml version="1.0" standalone="yes"?>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="{px}" height="{px}" viewBox="0 0 {side} {side}" shape-rendering="crispEdges">"#,
+            r#"<rect width="100%" height="100%" fill="{light}"/><path fill="{dark}" d="{path}"/></svg>"#
+        ),
+        px = pixels,
+        side = modules.side,
+        light = options.light_color,
+        dark = options.dark_color,
+        path = path,
+    )
+}
+
+// two modules per character, light on dark so it scans from a dark terminal
+fn render_text(modules: &Modules) -> String {
+    (0..modules.side)
+        .step_by(2)
+        .map(|y| {
+            (0..modules.side)
+                .map(|x| {
+                    let top = !modules.is_dark(x, y);
+                    let bottom = y + 1 < modules.side && !modules.is_dark(x, y + 1);
+                    match (top, bottom) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_png(modules: &Modules, options: &QrQuery) -> Result<Vec<u8>, ApiError> {
+    let (pixels, side) = modules.rgb(
+        modules.module_pixels(options.size),
+        options.dark_color,
+        options.light_color,
+    );
+
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, side as u32, side as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    // PNG stores pixels per metre
+    let per_metre = (f64::from(options.dpi) / 0.0254).round() as u32;
+    encoder.set_pixel_dims(Some(png::PixelDimensions {
+        xppu: per_metre,
+        yppu: per_metre,
+        unit: png::Unit::Meter,
+    }));
+
+    let mut writer = encoder.write_header().map_err(ApiError::internal)?;
+    writer.write_image_data(&pixels).map_err(ApiError::internal)?;
+    writer.finish().map_err(ApiError::internal)?;
+    Ok(bytes)
+}
+
+fn render_jpeg(modules: &Modules, options: &QrQuery) -> Result<Vec<u8>, ApiError> {
+    let (pixels, side) = modules.rgb(
+        modules.module_pixels(options.size),
+        options.dark_color,
+        options.light_color,
+    );
+
+    let mut bytes = Vec::new();
+    // high quality and no chroma subsampling, JPEG artifacts blur module edges
+    let mut encoder = jpeg_encoder::Encoder::new(&mut bytes, 95);
+    encoder.set_sampling_factor(jpeg_encoder::SamplingFactor::R_4_4_4);
+    encoder.set_density(jpeg_encoder::Density::Inch {
+        x: options.dpi as u16,
+        y: options.dpi as u16,
+    });
+    encoder
+        .encode(&pixels, side as u16, side as u16, jpeg_encoder::ColorType::Rgb)
+        .map_err(ApiError::internal)?;
+    Ok(bytes)
+}
+
+fn pdf_rgb(color: QrColor) -> (f32, f32, f32) {
+    let [r, g, b] = color.0.map(|c| f32::from(c) / 255.0);
+    (r, g, b)
+}
+
+// Draws the modules with their bottom left corner at (x, y), `module` points a module
+pub(crate) fn draw_qr_pdf(
+    content: &mut Content,
+    modules: &Modules,
+    x: f32,
+    y: f32,
+    module: f32,
+    options: &QrQuery,
+) {
+    let side = modules.side as f32 * module;
+    let (r, g, b) = pdf_rgb(options.light_color);
+    content.set_fill_rgb(r, g, b).rect(x, y, side, side).fill_nonzero();
+
+    let (r, g, b) = pdf_rgb(options.dark_color);
+    content.set_fill_rgb(r, g, b);
+    for row in 0..modules.side {
+        // PDF y grows upwards, QR rows downwards
+        let top = y + side - (row + 1) as f32 * module;
+        for (start, length) in modules.dark_runs(row) {
+            content.rect(x + start as f32 * module, top, length as f32 * module, module);
+        }
+    }
+    content.fill_nonzero();
+}
+
+fn render_pdf(modules: &Modules, options: &QrQuery) -> Result<Vec<u8>, ApiError> {
+    // the same physical size a PNG of these pixels printed at `dpi` would have
+    let module = modules.module_pixels(options.size) as f32 * 72.0 / options.dpi as f32;
+    let side = modules.side as f32 * module;
+
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let page_id = Ref::new(3);
+    let content_id = Ref::new(4);
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id).kids([page_id]).count(1);
+    pdf.page(page_id)
+        .media_box(Rect::new(0.0, 0.0, side, side))
+        .parent(page_tree_id)
+        .contents(content_id);
+
+    let mut content = Content::new();
+    draw_qr_pdf(&mut content, modules, 0.0, 0.0, module, options);
+    pdf.stream(content_id, &deflate(&content.finish())?)
+        .filter(Filter::FlateDecode);
+    Ok(pdf.finish())
+}
+
+// PDF content streams are mostly repeated rectangle operators, they shrink ~10x
+pub(crate) fn deflate(data: &[u8]) -> Result<Vec<u8>, ApiError> {
+    let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+    zlib.write_all(data).map_err(ApiError::internal)?;
+    zlib.finish().map_err(ApiError::internal)
+}
+
+// The QR code grid for `cert`, the input must already be validated
+pub(crate) fn qr_modules(
+    cert: &SignedCertificate,
+    options: &QrQuery,
+    verify_url: Option<&str>,
+) -> Result<Modules, ApiError> {
+    options.validate().map_err(ApiError::bad_request)?;
+    let data = qr_payload(cert, options.qr_payload, verify_url)?;
+
+    let ec_level = match options.ec_level {
+        QrEcLevel::Low => EcLevel::L,
+        QrEcLevel::Medium => EcLevel::M,
+        QrEcLevel::Quartile => EcLevel::Q,
+        QrEcLevel::High => EcLevel::H,
+    };
+
+    // The encoder picks the densest mode for the data, compact payloads are all alphanumeric
+    let qr_code = QrCode::with_error_correction_level(data.as_bytes(), ec_level).map_err(|e| match e {
+        QrError::DataTooLong => ApiError::bad_request(match options.qr_payload {
+            QrPayload::Json => "Certificate data too large for QR code, try qr_payload=compact or a lower ec_level",
+            QrPayload::Compact | QrPayload::Url => {
+                "Certificate data too large for QR code, try a lower ec_level"
+            }
+        }),
+        e => ApiError::internal(e),
+    })?;
+
+    Ok(Modules::new(&qr_code, options.quiet_zone as usize))
+}
+
+// Encodes the certificate as a QR code, the input must already be validated
+pub(crate) fn render_qr(
+    cert: &SignedCertificate,
+    options: &QrQuery,
+    verify_url: Option<&str>,
+) -> Result<RenderedQr, ApiError> {
+    let modules = qr_modules(cert, options, verify_url)?;
+
+    let bytes = match options.qr_format {
+        QrFormat::Svg => render_svg(&modules, options).into_bytes(),
+        QrFormat::Text => render_text(&modules).into_bytes(),
+        QrFormat::Png => render_png(&modules, options)?,
+        QrFormat::Jpeg => render_jpeg(&modules, options)?,
+        QrFormat::Pdf => render_pdf(&modules, options)?,
+    };
+
+    Ok(RenderedQr {
+        format: options.qr_format,
+        bytes,
+    })
+}
+
+// A render holds the whole image in memory, a 2048 pixel PNG or PDF is tens of MB while
+// it is built, so the server only runs a few at a time
+const RENDER_CONCURRENCY: usize = 4;
+static RENDER_PERMITS: Semaphore = Semaphore::const_new(RENDER_CONCURRENCY);
+
+// render_qr for the HTTP routes, waits for a render slot and keeps the work off the
+// async workers
+pub(crate) async fn render_qr_limited(
+    cert: SignedCertificate,
+    options: QrQuery,
+    verify_url: Option<String>,
+) -> Result<RenderedQr, ApiError> {
+    let _permit = RENDER_PERMITS.acquire().await.map_err(ApiError::internal)?;
+
+    tokio::task::spawn_blocking(move || render_qr(&cert, &options, verify_url.as_deref()))
+        .await
+        .map_err(ApiError::internal)?
+}
 
 #[utoipa::path(
     post,
     path = "/qr_code",
     request_body = SignedCertificate,
+    params(QrQuery),
     responses(
-        (status = 200, description = "QR code generated successfully", body = String),
-        (status = 400, description = "Invalid input", body = String),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 200, description = "QR code generated successfully", content(
+            (String = "image/svg+xml"),
+            (String = "text/plain"),
+            (Vec<u8> = "image/png"),
+            (Vec<u8> = "image/jpeg"),
+            (Vec<u8> = "application/pdf")
+        )),
+        (status = 400, description = "Invalid input or render options, or qr_payload=url without a verify_url configured", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
     )
 )]
 pub async fn generate_qr_code(
+    State(state): State<AppState>,
+    Query(query): Query<QrQuery>,
     Json(cert): Json<SignedCertificate>,
-) -> Result<(StatusCode, String), (StatusCode, String)> {
+) -> Result<RenderedQr, ApiError> {
     // to validate input
-    if let Err(errors) = cert.validate() {
-        return Err((StatusCode::BAD_REQUEST, errors.to_string()));
-    }
+    cert.validate().map_err(ApiError::bad_request)?;
 
-    let cert_str = serde_json::to_string(&cert)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    render_qr_limited(cert, query, state.config.server.verify_url.clone()).await
+}
 
-    // check QR code size limit
-    if cert_str.len() > 2953 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "Certificate data too large for QR code".to_string(),
-        ));
-    }
+#[utoipa::path(
+    post,
+    path = "/qr_payload/decode",
+    request_body = QrPayloadInput,
+    responses(
+        (status = 200, description = "The certificate a scanned QR code holds, ready for /verify_authenticity", body = SignedCertificate),
+        (status = 400, description = "Not an ERI certificate payload, or an unsupported version", body = ErrorResponse)
+    )
+)]
+pub async fn decode_qr_code_payload(
+    Json(input): Json<QrPayloadInput>,
+) -> Result<Json<SignedCertificate>, ApiError> {
+    parse_qr_text(&input.payload).map(Json)
+}
 
-    // EcLevel means medium error correction
-    let qr_code = QrCode::with_error_correction_level(cert_str.as_bytes(), EcLevel::M)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+// The certificate a scanned QR code holds, in any payload the server renders
+pub(crate) fn parse_qr_text(payload: &str) -> Result<SignedCertificate, ApiError> {
+    // links carry the compact payload as their last path segment, after /v/
+    let payload = if payload.starts_with("https://") || payload.starts_with("http://") {
+        payload
+            .rsplit_once("/v/")
+            .map(|(_, token)| token.split(['?', '#']).next().unwrap_or_default())
+            .ok_or_else(|| ApiError::bad_request("Not an ERI verification link"))?
+    } else {
+        payload
+    };
 
-    // render as SVG
-    let svg = qr_code
-        .render::<svg::Color>()
-        .min_dimensions(200, 200)
-        .build();
+    // older codes hold the SignedCertificate JSON itself
+    let cert = if payload.trim_start().starts_with('{') {
+        serde_json::from_str::<SignedCertificate>(payload).map_err(ApiError::bad_request)?
+    } else {
+        let (certificate, signature) = decode_qr_payload(payload).map_err(ApiError::bad_request)?;
+        SignedCertificate {
+            name: certificate.name,
+            unique_id: certificate.unique_id,
+            serial: certificate.serial,
+            date: certificate.date.as_u64(),
+            owner: format!("{:?}", certificate.owner),
+            metadata: certificate.metadata,
+            signature: format!("0x{}", signature),
+        }
+    };
 
-    Ok((StatusCode::OK, svg))
-}
\ No newline at end of file
+    cert.validate().map_err(ApiError::bad_request)?;
+    Ok(cert)
+}
@@ -0,0 +1,48 @@
+use crate::config::app_router::Authenticity;
+use crate::config::app_state::AppState;
+use crate::error::ApiError;
+use crate::models::events::ManufacturerRegistered;
+use crate::utility::{decode_receipt_event, resolve_manufacturer_name};
+use ethers::prelude::*;
+
+// Registers the server wallet as manufacturer `name`, returns the registered address and
+// name once the transaction is mined
+pub(crate) async fn register_manufacturer(
+    state: &AppState,
+    name: String,
+) -> Result<(Address, String), ApiError> {
+    let contract = Authenticity::new(state.authenticity_contract, state.eth_client.clone());
+
+    let receipt = contract
+        .manufacturer_registers(name)
+        .send()
+        .await?
+        .await?
+        .ok_or_else(|| ApiError::Rpc("transaction dropped from the mempool".to_string()))?;
+
+    if receipt.status != Some(1.into()) {
+        return Err(ApiError::Reverted(format!(
+            "transaction {:?} reverted",
+            receipt.transaction_hash
+        )));
+    }
+
+    let event: ManufacturerRegistered = decode_receipt_event(&receipt, state.authenticity_contract)
+        .ok_or_else(|| {
+            ApiError::internal(format!(
+                "ManufacturerRegistered event not found in {:?}",
+                receipt.transaction_hash
+            ))
+        })?;
+
+    state.manufacturers.invalidate(event.manufacturer_address);
+
+    // the log only carries the name's hash, read the name itself back from the registry
+    let manufacturer_name = resolve_manufacturer_name(&contract, &event).await?;
+
+    println!("📦 Manufacturer Registers:");
+    println!("    Manufacturer Address: {}", event.manufacturer_address);
+    println!("    Manufacturer Name: {:?}", manufacturer_name);
+
+    Ok((event.manufacturer_address, manufacturer_name))
+}
@@ -0,0 +1,308 @@
+use crate::config::app_router::Authenticity;
+use crate::config::app_state::AppState;
+use crate::error::{ApiError, ErrorResponse};
+use crate::models::events::ManufacturerRegistered;
+use crate::models::certificate_model::SignedCertificate;
+use crate::models::verification_model::{
+    OfflineVerificationResult, OfflineVerifyInput, VerificationResult,
+};
+use crate::services::verify_authenticity::{parse_certificate, rejection};
+use axum::{Json, extract::State};
+use eri_core::{RegistryEntry, RegistrySnapshot, SignedRegistrySnapshot};
+use ethers::prelude::*;
+use std::collections::BTreeSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Snapshots older than this are flagged stale unless the caller says otherwise
+const DEFAULT_MAX_AGE_SECS: u64 = 24 * 60 * 60;
+
+#[utoipa::path(
+    get,
+    path = "/registry_snapshot",
+    responses(
+        (status = 200, description = "Registered manufacturers as of the last indexed block, signed by the server wallet", body = Object),
+        (status = 404, description = "Nothing indexed yet, or the indexer is disabled", body = ErrorResponse),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
+    )
+)]
+pub async fn registry_snapshot(
+    State(state): State<AppState>,
+) -> Result<Json<SignedRegistrySnapshot>, ApiError> {
+    // pin everything to the indexer checkpoint so the event list and the names agree
+    let checkpoint = state
+        .event_store()?
+        .checkpoint()?
+        .ok_or_else(|| ApiError::NotFound("indexer has not processed any block yet".to_string()))?;
+
+    let block = state
+        .eth_client
+        .get_block(checkpoint.number)
+        .await
+        .map_err(|e| ApiError::Rpc(e.to_string()))?
+        .ok_or_else(|| ApiError::Rpc(format!("block {} not found", checkpoint.number)))?;
+    if block.hash != Some(checkpoint.hash) {
+        return Err(ApiError::Rpc(format!(
+            "block {} was reorged, retry once the indexer catches up",
+            checkpoint.number
+        )));
+    }
+
+    let addresses: BTreeSet<Address> = state
+        .event_store()?
+        .events_named("ManufacturerRegistered")?
+        .iter()
+        .filter_map(|event| event.decode::<ManufacturerRegistered>())
+        .map(|event| event.manufacturer_address)
+        .collect();
+
+    let contract = Authenticity::new(state.authenticity_contract, state.eth_client.clone());
+    let mut manufacturers = Vec::with_capacity(addresses.len());
+    for address in addresses {
+        let manufacturer = contract
+            .get_manufacturer(address)
+            .block(checkpoint.number)
+            .call()
+            .await?;
+        manufacturers.push(RegistryEntry {
+            address,
+            name: manufacturer.name,
+        });
+    }
+
+    let snapshot = RegistrySnapshot {
+        chain_id: state.chain_id,
+        contract: state.authenticity_contract,
+        domain_name: state.config.signing.domain.clone(),
+        domain_version: state.config.signing.version.clone(),
+        block_number: checkpoint.number,
+        block_hash: checkpoint.hash,
+        timestamp: block.timestamp.as_u64(),
+        manufacturers,
+    };
+
+    let signed = snapshot
+        .sign(state.eth_client.signer())
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    println!(
+        "📦 Registry snapshot at block {} with {} manufacturers",
+        signed.snapshot.block_number,
+        signed.snapshot.manufacturers.len()
+    );
+    Ok(Json(signed))
+}
+
+// Verdict for `cert` against a snapshot signed by `trusted_signer`, with no RPC at all.
+// Shared by POST /verify_offline and `eri verify --snapshot`
+pub(crate) fn verify_with_snapshot(
+    cert: &SignedCertificate,
+    signed: &SignedRegistrySnapshot,
+    trusted_signer: Address,
+    max_age_secs: Option<u64>,
+) -> Result<OfflineVerificationResult, ApiError> {
+    let snapshot = signed
+        .verify(trusted_signer)
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    let claimed_owner = cert.owner.clone();
+    let verdict =
+        parse_certificate(cert, snapshot.domain()).and_then(|(certificate, signature)| {
+            eri_core::verify_offline(snapshot, certificate, &signature).map_err(rejection)
+        });
+    let result = match verdict {
+        Ok((signer, name)) => VerificationResult::authentic(
+            signer,
+            claimed_owner,
+            name,
+            snapshot.chain_id,
+            snapshot.contract,
+        ),
+        Err((reason, signer)) => VerificationResult::rejected(
+            reason,
+            signer,
+            claimed_owner,
+            snapshot.chain_id,
+            snapshot.contract,
+        ),
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .as_secs();
+    let snapshot_age_secs = snapshot.age_secs(now);
+
+    Ok(OfflineVerificationResult {
+        result,
+        snapshot_block: snapshot.block_number,
+        snapshot_block_hash: snapshot.block_hash,
+        snapshot_age_secs,
+        stale: snapshot_age_secs > max_age_secs.unwrap_or(DEFAULT_MAX_AGE_SECS),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/verify_offline",
+    request_body = OfflineVerifyInput,
+    responses(
+        (status = 200, description = "Verification verdict against the snapshot, authentic or not", body = OfflineVerificationResult),
+        (status = 400, description = "Snapshot was not signed by this server or was modified", body = ErrorResponse)
+    )
+)]
+pub async fn verify_offline(
+    State(state): State<AppState>,
+    Json(input): Json<OfflineVerifyInput>,
+) -> Result<Json<OfflineVerificationResult>, ApiError> {
+    // no RPC from here on, the snapshot stands in for getManufacturer
+    let result = verify_with_snapshot(
+        &input.certificate,
+        &input.snapshot,
+        state.eth_client.signer().address(),
+        input.max_age_secs,
+    )?;
+
+    Ok(Json(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::verification_model::VerificationFailure;
+    use eri_core::{Certificate, sign_certificate};
+
+    const SERVER_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const MANUFACTURER_KEY: &str =
+        "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+    const OTHER_KEY: &str = "5de4111afa1a4b94908f83103eb1f1706367c2e68ca870fc3fb9a804cdab365a";
+
+    fn wallet(key: &str) -> LocalWallet {
+        key.parse().unwrap()
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    // signed by the server key, taken `age_secs` ago
+    async fn snapshot(age_secs: u64) -> SignedRegistrySnapshot {
+        RegistrySnapshot {
+            chain_id: 31337,
+            contract: Address::repeat_byte(0x11),
+            domain_name: "ERI".to_string(),
+            domain_version: "1".to_string(),
+            block_number: 100,
+            block_hash: H256::repeat_byte(0xbb),
+            timestamp: now() - age_secs,
+            manufacturers: vec![RegistryEntry {
+                address: wallet(MANUFACTURER_KEY).address(),
+                name: "Acme".to_string(),
+            }],
+        }
+        .sign(&wallet(SERVER_KEY))
+        .await
+        .unwrap()
+    }
+
+    async fn certificate(key: &str, snapshot: &SignedRegistrySnapshot) -> SignedCertificate {
+        let signer = wallet(key);
+        let certificate = Certificate::new(
+            "Phone".to_string(),
+            "IMEI1".to_string(),
+            "SN1".to_string(),
+            1_700_000_000,
+            signer.address(),
+            vec!["red".to_string()],
+        )
+        .with_domain(snapshot.snapshot.domain());
+        let signature = sign_certificate(&signer, &certificate).await.unwrap();
+
+        SignedCertificate {
+            name: certificate.name,
+            unique_id: certificate.unique_id,
+            serial: certificate.serial,
+            date: 1_700_000_000,
+            owner: format!("{:?}", signer.address()),
+            metadata: certificate.metadata,
+            signature: format!("0x{}", signature),
+        }
+    }
+
+    #[tokio::test]
+    async fn fresh_snapshot_verifies_a_registered_manufacturer() {
+        let signed = snapshot(60).await;
+        let cert = certificate(MANUFACTURER_KEY, &signed).await;
+
+        let result =
+            verify_with_snapshot(&cert, &signed, wallet(SERVER_KEY).address(), None).unwrap();
+
+        assert!(result.result.authentic);
+        assert_eq!(result.result.manufacturer_name.as_deref(), Some("Acme"));
+        assert_eq!(result.snapshot_block, 100);
+        assert!(result.snapshot_age_secs >= 60);
+        assert!(!result.stale);
+    }
+
+    #[tokio::test]
+    async fn snapshot_past_max_age_is_stale() {
+        let signed = snapshot(600).await;
+        let cert = certificate(MANUFACTURER_KEY, &signed).await;
+        let trusted = wallet(SERVER_KEY).address();
+
+        let result = verify_with_snapshot(&cert, &signed, trusted, Some(300)).unwrap();
+        // still a verdict, the caller decides what stale means
+        assert!(result.result.authentic);
+        assert!(result.stale);
+
+        assert!(
+            !verify_with_snapshot(&cert, &signed, trusted, Some(3600))
+                .unwrap()
+                .stale
+        );
+        // a day by default
+        let old = snapshot(DEFAULT_MAX_AGE_SECS + 60).await;
+        assert!(
+            verify_with_snapshot(&cert, &old, trusted, None)
+                .unwrap()
+                .stale
+        );
+    }
+
+    #[tokio::test]
+    async fn unregistered_signer_is_not_authentic() {
+        let signed = snapshot(60).await;
+        let cert = certificate(OTHER_KEY, &signed).await;
+
+        let result =
+            verify_with_snapshot(&cert, &signed, wallet(SERVER_KEY).address(), None).unwrap();
+
+        assert!(!result.result.authentic);
+        assert!(matches!(
+            result.result.failure_reason,
+            Some(VerificationFailure::ManufacturerNotRegistered)
+        ));
+    }
+
+    #[tokio::test]
+    async fn tampered_or_untrusted_snapshot_is_refused() {
+        let signed = snapshot(60).await;
+        let cert = certificate(OTHER_KEY, &signed).await;
+
+        // slipping the counterfeiter into the registry breaks the signature
+        let mut tampered = signed.clone();
+        tampered.snapshot.manufacturers[0].address = wallet(OTHER_KEY).address();
+        assert!(matches!(
+            verify_with_snapshot(&cert, &tampered, wallet(SERVER_KEY).address(), None),
+            Err(ApiError::BadRequest(_))
+        ));
+
+        assert!(matches!(
+            verify_with_snapshot(&cert, &signed, wallet(OTHER_KEY).address(), None),
+            Err(ApiError::BadRequest(_))
+        ));
+    }
+}
@@ -1,105 +1,386 @@
 use crate::models::certificate_model::{
     Certificate, SignedCertificate,
 };
-use crate::config::app_state::AppState;
-use axum::{extract::State, http::StatusCode, Json};
-use ethers::types::transaction::eip712::Eip712;
-use ethers::{
-    contract::EthEvent,
-    prelude::*,
-    signers::Signer,
-    types::Signature,
+use crate::models::verification_model::{
+    BatchVerdict, BatchVerificationResult, ManufacturerCacheStats, VerificationFailure,
+    VerificationResult,
 };
-use std::error::Error;
+use crate::config::app_state::AppState;
+use crate::config::app_router::{Authenticity, EriErrorsErrors};
+use crate::error::{ApiError, ErrorResponse};
+use axum::{extract::State, Json};
+use eri_core::{CertificateError, parse_signature, verify_owner_signature};
+use ethers::prelude::*;
+use ethers::types::transaction::eip712::EIP712Domain;
+use rayon::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 use validator::Validate;
-use crate::config::app_router::{authenticity, Authenticity};
 
+// A failed check, with the signer when we got far enough to recover one
+pub(crate) type Rejection = (VerificationFailure, Option<Address>);
+
+// Input validation and signature parsing, the certificate is hashed under `domain`
+pub(crate) fn parse_certificate(
+    cert: &SignedCertificate,
+    domain: EIP712Domain,
+) -> Result<(Certificate, Signature), Rejection> {
+    // to validate input
+    if let Err(errors) = cert.validate() {
+        eprintln!("Invalid certificate: {}", errors);
+        return Err((VerificationFailure::InvalidCertificate, None));
+    }
+
+    let certificate = Certificate::try_from(cert.clone())
+        .map_err(|e| {
+            eprintln!("Certificate conversion error: {:?}", e);
+            (VerificationFailure::InvalidCertificate, None)
+        })?
+        .with_domain(domain);
+
+    let signature = parse_signature(&cert.signature).map_err(|e| {
+        eprintln!("Invalid signature format: {:?}", e);
+        (VerificationFailure::InvalidSignatureFormat, None)
+    })?;
+
+    Ok((certificate, signature))
+}
+
+// The verdict for an error from recovering or checking the signer
+pub(crate) fn rejection(error: CertificateError) -> Rejection {
+    match error {
+        CertificateError::SignerNotOwner { signer, .. } => {
+            (VerificationFailure::SignerNotOwner, Some(signer))
+        }
+        CertificateError::ManufacturerNotRegistered(signer) => {
+            (VerificationFailure::ManufacturerNotRegistered, Some(signer))
+        }
+        CertificateError::Eip712(e) => {
+            eprintln!("EIP-712 encoding error: {:?}", e);
+            (VerificationFailure::InvalidCertificate, None)
+        }
+        e => {
+            eprintln!("Signer recovery error: {:?}", e);
+            (VerificationFailure::SignerRecoveryFailed, None)
+        }
+    }
+}
+
+// Everything that can be checked without the chain: input validation,
+// signature parsing, signer recovery and the signer == owner check
+pub(crate) fn recover_signer(
+    cert: &SignedCertificate,
+    domain: EIP712Domain,
+) -> Result<(Certificate, Address), Rejection> {
+    let (certificate, signature) = parse_certificate(cert, domain)?;
+
+    // Recover the signer and make sure it is the certificate owner
+    let signer = verify_owner_signature(&certificate, &signature).map_err(rejection)?;
+
+    Ok((certificate, signer))
+}
+
+// The verdict for a certificate once its signer has been recovered and looked up on
+// chain (None when it is not a registered manufacturer). Every verification path ends here
+pub(crate) fn verdict(
+    state: &AppState,
+    claimed_owner: String,
+    recovered: Result<(Address, Option<String>), Rejection>,
+) -> VerificationResult {
+    let chain_id = state.chain_id;
+    let contract_address = state.authenticity_contract;
+
+    match recovered {
+        Ok((signer, Some(name))) => {
+            VerificationResult::authentic(signer, claimed_owner, name, chain_id, contract_address)
+        }
+        Ok((signer, None)) => VerificationResult::rejected(
+            VerificationFailure::ManufacturerNotRegistered,
+            Some(signer),
+            claimed_owner,
+            chain_id,
+            contract_address,
+        ),
+        Err((reason, signer)) => {
+            VerificationResult::rejected(reason, signer, claimed_owner, chain_id, contract_address)
+        }
+    }
+}
+
+// Full verdict for one certificate. Only chain failures come back as errors,
+// every problem with the certificate itself is answered with authentic = false
+pub(crate) async fn verify_certificate(
+    state: &AppState,
+    cert: &SignedCertificate,
+) -> Result<VerificationResult, ApiError> {
+    let recovered = match recover_signer(cert, state.eip712_domain()) {
+        Ok((_, signer)) => Ok((signer, lookup_manufacturer(state, signer).await?)),
+        Err(rejection) => Err(rejection),
+    };
+
+    Ok(verdict(state, cert.owner.clone(), recovered))
+}
+
+// Name `signer` is registered under on the Authenticity contract, None when it is not a manufacturer
+pub(crate) async fn lookup_manufacturer(
+    state: &AppState,
+    signer: Address,
+) -> Result<Option<String>, ApiError> {
+    if let Some(name) = state.manufacturers.get(signer) {
+        return Ok(name);
+    }
+
+    let generation = state.manufacturers.generation();
+    let name = fetch_manufacturer(state, signer).await?;
+    state.manufacturers.insert(signer, name.clone(), generation);
+    Ok(name)
+}
+
+async fn fetch_manufacturer(state: &AppState, signer: Address) -> Result<Option<String>, ApiError> {
+    let contract = Authenticity::new(state.authenticity_contract, state.eth_client.clone());
+
+    let manufacturer = match contract.get_manufacturer(signer).call().await.map_err(ApiError::from) {
+        Ok(manufacturer) => manufacturer,
+        Err(ApiError::Contract(EriErrorsErrors::DOES_NOT_EXIST(_))) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    eprintln!("Manufacturer Address: {:?}", manufacturer.manufacturer_address);
+
+    // getManufacturer is keyed by the signer, so a mismatch means the registry entry is not theirs
+    if signer != manufacturer.manufacturer_address {
+        return Ok(None);
+    }
+
+    Ok(Some(manufacturer.name))
+}
 
 #[utoipa::path(
     post,
     path = "/verify_authenticity",
     request_body = SignedCertificate,
     responses(
-        (status = 200, description = "Signature verification result", body = String),
-        (status = 400, description = "Invalid input"),
-        (status = 500, description = "Internal server error")
+        (status = 200, description = "Verification verdict, authentic or not", body = VerificationResult),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
     )
 )]
 pub async fn verify_authenticity(
     State(state): State<AppState>,
     Json(cert): Json<SignedCertificate>,
-) -> Result<Json<(String, String)>, StatusCode> {
-    let certificate: Certificate = cert
-        .clone()
-        .try_into()
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
+) -> Result<Json<VerificationResult>, ApiError> {
+    let result = verify_certificate(&state, &cert).await?;
 
-    // to validate input
-    cert.validate()
-        .expect(&*StatusCode::BAD_REQUEST.to_string());
+    eprintln!("Verification result: {:?}", result);
+    Ok(Json(result))
+}
 
-    // Parse the signature from hex string
-    let signature_bytes = hex::decode(cert.signature.trim_start_matches("0x")).map_err(|e| {
-        eprintln!("Invalid signature format: {:?}", e);
-        StatusCode::BAD_REQUEST
-    })?;
+// Largest batch accepted in one request
+const MAX_BATCH_SIZE: usize = 1000;
+// getManufacturer calls in flight at once
+const LOOKUP_CONCURRENCY: usize = 16;
 
-    eprintln!("Signature Byte: {:?}", signature_bytes);
+#[utoipa::path(
+    post,
+    path = "/verify_authenticity/batch",
+    request_body = Vec<SignedCertificate>,
+    responses(
+        (status = 200, description = "One verdict per certificate, in request order", body = BatchVerificationResult),
+        (status = 400, description = "Empty batch or more than 1000 certificates", body = ErrorResponse)
+    )
+)]
+pub async fn verify_authenticity_batch(
+    State(state): State<AppState>,
+    Json(certs): Json<Vec<SignedCertificate>>,
+) -> Result<Json<BatchVerificationResult>, ApiError> {
+    if certs.is_empty() || certs.len() > MAX_BATCH_SIZE {
+        return Err(ApiError::bad_request(format!(
+            "a batch holds between 1 and {} certificates, got {}",
+            MAX_BATCH_SIZE,
+            certs.len()
+        )));
+    }
 
-    let signature = Signature::try_from(signature_bytes.as_slice()).map_err(|e| {
-        eprintln!("Signature parsing error: {:?}", e);
-        StatusCode::BAD_REQUEST
-    })?;
+    // recovery is pure CPU work, keep it off the async workers
+    let domain = state.eip712_domain();
+    let (certs, recovered) = tokio::task::spawn_blocking(move || {
+        let recovered: Vec<Result<Address, Rejection>> = certs
+            .par_iter()
+            .map(|cert| recover_signer(cert, domain.clone()).map(|(_, signer)| signer))
+            .collect();
+        (certs, recovered)
+    })
+    .await
+    .map_err(ApiError::internal)?;
 
-    eprintln!("Signature: {:?}", signature);
+    // one getManufacturer call per distinct signer, however many certificates it signed
+    let signers: BTreeSet<Address> = recovered
+        .iter()
+        .filter_map(|r| r.as_ref().ok())
+        .copied()
+        .collect();
+    let permits = Arc::new(Semaphore::new(LOOKUP_CONCURRENCY));
+    let mut lookups = JoinSet::new();
+    for signer in signers {
+        let state = state.clone();
+        let permits = permits.clone();
+        lookups.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            (signer, lookup_manufacturer(&state, signer).await)
+        });
+    }
 
-    // Compute the EIP-712 digest
-    let digest = certificate.encode_eip712().map_err(|e| {
-        eprintln!("EIP-712 encoding error: {:?}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let mut manufacturers = HashMap::new();
+    while let Some(lookup) = lookups.join_next().await {
+        let (signer, manufacturer) = lookup.map_err(ApiError::internal)?;
+        manufacturers.insert(signer, manufacturer.map_err(|e| ErrorResponse::from(&e)));
+    }
 
-    //this caused big issue until I removed it
-    // let digest = hash_message(digest); // Prefix with \x19Ethereum Signed Message
+    let verdicts: Vec<BatchVerdict> = certs
+        .into_iter()
+        .zip(recovered)
+        .enumerate()
+        .map(|(index, (cert, recovered))| {
+            let result = match recovered {
+                Ok(signer) => manufacturers[&signer]
+                    .clone()
+                    .map(|name| verdict(&state, cert.owner, Ok((signer, name)))),
+                Err(rejection) => Ok(verdict(&state, cert.owner, Err(rejection))),
+            };
 
-    eprintln!("Digest: {:?}", digest);
+            match result {
+                Ok(result) => BatchVerdict {
+                    index,
+                    result: Some(result),
+                    error: None,
+                },
+                Err(error) => BatchVerdict {
+                    index,
+                    result: None,
+                    error: Some(error),
+                },
+            }
+        })
+        .collect();
 
-    // Recover the signer
-    let signer = signature.recover(digest).map_err(|e| {
-        eprintln!("Signer recovery error: {:?}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let authentic = verdicts
+        .iter()
+        .filter(|v| v.result.as_ref().is_some_and(|r| r.authentic))
+        .count();
+    let failed = verdicts.iter().filter(|v| v.error.is_some()).count();
 
-    eprintln!("Signer: {:?}", signer);
-    // very important: double check to make sure the certificate owner is the signer of the signature
-    assert_eq!(signer, certificate.owner);
-    // Fetch the contract's owner
-    let contract = Authenticity::new(state.authenticity_contract, state.eth_client.clone());
+    println!(
+        "🔍 Verified batch of {}: {} authentic, {} failed",
+        verdicts.len(),
+        authentic,
+        failed
+    );
+    Ok(Json(BatchVerificationResult {
+        total: verdicts.len(),
+        authentic,
+        rejected: verdicts.len() - authentic - failed,
+        failed,
+        verdicts,
+    }))
+}
 
-    let manufacturer: authenticity::Manufacturer = contract
-        .get_manufacturer(signer)
-        .call()
-        .await
-        .map_err(|e| {
-            eprintln!("Contract call error: {:?}", e.to_string());
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+#[utoipa::path(
+    get,
+    path = "/manufacturer_cache",
+    responses(
+        (status = 200, description = "Hit and miss counters of the getManufacturer cache", body = ManufacturerCacheStats)
+    )
+)]
+pub async fn manufacturer_cache_stats(
+    State(state): State<AppState>,
+) -> Json<ManufacturerCacheStats> {
+    Json(state.manufacturers.stats())
+}
 
-    eprintln!("Manufacturer Address: {:?}", manufacturer.manufacturer_address);
-    // Verify the signer matches the owner
-    if signer == manufacturer.manufacturer_address {
-        Ok(Json((manufacturer.manufacturer_address.to_string(), manufacturer.name)))
-        //     format!(
-        //     "Signature is valid! Signed by owner: {:?}",
-        //     signer
-        // ))
-        // )
-    } else {
-        Ok(Json( (signer.to_string(), manufacturer.name) ))
-        //     format!(
-        //     "Signature is invalid. Recovered signer: {:?}, expected owner: {:?}",
-        //     signer, manufacturer.manufacturer_address
-        // ))
-        // )
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::registry::KeyRegistry;
+
+    const OWNER: &str = "0x70997970c51812dc3a010c7d01b50e0d17dc79c8";
+
+    fn signer() -> Address {
+        OWNER.parse().unwrap()
+    }
+
+    #[test]
+    fn registered_signer_is_authentic() {
+        let state = AppState::for_tests(KeyRegistry::empty());
+
+        let result = verdict(
+            &state,
+            OWNER.to_string(),
+            Ok((signer(), Some("Acme".to_string()))),
+        );
+
+        assert!(result.authentic);
+        assert_eq!(result.signer, Some(signer()));
+        assert_eq!(result.manufacturer_name.as_deref(), Some("Acme"));
+        assert!(result.failure_reason.is_none());
+        assert_eq!(result.chain_id, 31337);
+        assert_eq!(result.contract, state.authenticity_contract);
+    }
+
+    #[test]
+    fn unregistered_signer_is_rejected() {
+        let state = AppState::for_tests(KeyRegistry::empty());
+
+        let result = verdict(&state, OWNER.to_string(), Ok((signer(), None)));
+
+        assert!(!result.authentic);
+        assert_eq!(result.signer, Some(signer()));
+        assert!(matches!(
+            result.failure_reason,
+            Some(VerificationFailure::ManufacturerNotRegistered)
+        ));
+    }
+
+    #[test]
+    fn rejection_keeps_its_reason_and_signer() {
+        let state = AppState::for_tests(KeyRegistry::empty());
+        let impostor = Address::repeat_byte(0x0f);
+
+        let result = verdict(
+            &state,
+            OWNER.to_string(),
+            Err((VerificationFailure::SignerNotOwner, Some(impostor))),
+        );
+
+        assert!(!result.authentic);
+        assert_eq!(result.signer, Some(impostor));
+        assert_eq!(result.claimed_owner, OWNER);
+        assert!(result.manufacturer_name.is_none());
+        assert!(matches!(
+            result.failure_reason,
+            Some(VerificationFailure::SignerNotOwner)
+        ));
+    }
+
+    #[test]
+    fn malformed_certificate_is_rejected_before_the_chain() {
+        let state = AppState::for_tests(KeyRegistry::empty());
+        let cert = SignedCertificate {
+            name: "Phone".to_string(),
+            unique_id: "IMEI1".to_string(),
+            serial: "SN1".to_string(),
+            date: 1_700_000_000,
+            owner: OWNER.to_string(),
+            metadata: vec!["red".to_string()],
+            signature: format!("0x{}", "11".repeat(65)),
+        };
+
+        let (reason, signer) = recover_signer(&cert, state.eip712_domain()).unwrap_err();
+
+        assert!(matches!(
+            reason,
+            VerificationFailure::InvalidSignatureFormat
+        ));
+        assert!(signer.is_none());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,277 @@
+use crate::config::app_state::AppState;
+use crate::error::{ApiError, ErrorResponse};
+use crate::models::certificate_model::SignedCertificate;
+use crate::models::verification_model::{QrVerificationResult, VerificationFailure};
+use crate::services::qr_code::parse_qr_text;
+use crate::services::verify_authenticity::verify_certificate;
+use axum::body::Bytes;
+use axum::extract::{FromRequest, Multipart, Path, Request, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{Html, IntoResponse, Response};
+use axum::Json;
+use image::imageops::FilterType;
+use image::{ImageReader, Limits};
+use std::io::Cursor;
+
+// Phone photos are 12+ megapixels, a label's QR code still spans hundreds of pixels
+// after scaling down to this, and detection gets much faster
+const MAX_SCAN_SIDE: u32 = 2048;
+// refuse to even decode anything larger, a small upload can claim huge dimensions
+const MAX_IMAGE_SIDE: u32 = 16_384;
+
+// The uploaded image, sent as the raw body or as the `image` field of a multipart form
+async fn read_upload(state: &AppState, request: Request) -> Result<Bytes, ApiError> {
+    let content_type = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+
+    if !content_type.starts_with("multipart/form-data") {
+        return Bytes::from_request(request, state)
+            .await
+            .map_err(ApiError::bad_request);
+    }
+
+    let mut multipart = Multipart::from_request(request, state)
+        .await
+        .map_err(ApiError::bad_request)?;
+    while let Some(field) = multipart.next_field().await.map_err(ApiError::bad_request)? {
+        if field.name() == Some("image") {
+            return field.bytes().await.map_err(ApiError::bad_request);
+        }
+    }
+    Err(ApiError::bad_request("No `image` field in the form"))
+}
+
+// Every QR code found in the image, decoded to text
+fn scan_qr_codes(bytes: &[u8]) -> Result<Vec<String>, ApiError> {
+    let mut reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(ApiError::bad_request)?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_SIDE);
+    limits.max_image_height = Some(MAX_IMAGE_SIDE);
+    reader.limits(limits);
+
+    let mut image = reader
+        .decode()
+        .map_err(|e| ApiError::bad_request(format!("Unreadable image: {}", e)))?;
+    if image.width().max(image.height()) > MAX_SCAN_SIDE {
+        image = image.resize(MAX_SCAN_SIDE, MAX_SCAN_SIDE, FilterType::Triangle);
+    }
+
+    let mut prepared = rqrr::PreparedImage::prepare(image.to_luma8());
+    let texts = prepared
+        .detect_grids()
+        .into_iter()
+        .filter_map(|grid| match grid.decode() {
+            Ok((_, text)) => Some(text),
+            Err(e) => {
+                eprintln!("Unreadable QR code in upload: {}", e);
+                None
+            }
+        })
+        .collect();
+
+    Ok(texts)
+}
+
+#[utoipa::path(
+    post,
+    path = "/verify/qr",
+    request_body(
+        content(
+            (Vec<u8> = "image/jpeg"),
+            (Vec<u8> = "image/png"),
+            (Vec<u8> = "image/webp"),
+            (Vec<u8> = "multipart/form-data")
+        ),
+        description = "Photo of a certificate QR code, as the raw body or the `image` field of a form"
+    ),
+    responses(
+        (status = 200, description = "The certificate the QR code holds and its verdict, authentic or not", body = QrVerificationResult),
+        (status = 400, description = "Unreadable image, no QR code found, or the QR code holds no ERI certificate", body = ErrorResponse),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
+    )
+)]
+pub async fn verify_qr_image(
+    State(state): State<AppState>,
+    request: Request,
+) -> Result<Json<QrVerificationResult>, ApiError> {
+    let bytes = read_upload(&state, request).await?;
+
+    // detection is CPU bound, keep it off the async workers
+    let texts = tokio::task::spawn_blocking(move || scan_qr_codes(&bytes))
+        .await
+        .map_err(ApiError::internal)??;
+    if texts.is_empty() {
+        return Err(ApiError::bad_request("No QR code found in the image"));
+    }
+
+    // a label can carry other codes (a product link, a GS1 code), use the first ERI one
+    let certificate: SignedCertificate = texts
+        .iter()
+        .find_map(|text| parse_qr_text(text).ok())
+        .ok_or_else(|| {
+            ApiError::bad_request(format!(
+                "Found {} QR codes, none holds an ERI certificate",
+                texts.len()
+            ))
+        })?;
+
+    let result = verify_certificate(&state, &certificate).await?;
+
+    eprintln!("QR verification result: {:?}", result);
+    Ok(Json(QrVerificationResult {
+        certificate,
+        result,
+    }))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn failure_text(reason: Option<VerificationFailure>) -> &'static str {
+    match reason {
+        Some(VerificationFailure::InvalidCertificate) => {
+            "The certificate is incomplete or malformed."
+        }
+        Some(VerificationFailure::InvalidSignatureFormat) => {
+            "The certificate signature is malformed."
+        }
+        Some(VerificationFailure::SignerRecoveryFailed) => "The certificate signature is invalid.",
+        Some(VerificationFailure::SignerNotOwner) => {
+            "The certificate was not signed by the manufacturer it names. It may have been altered."
+        }
+        Some(VerificationFailure::ManufacturerNotRegistered) => {
+            "The certificate was signed by someone who is not a registered manufacturer."
+        }
+        Some(VerificationFailure::NotInBatch) => {
+            "The certificate is not part of the batch it claims."
+        }
+        None => "The certificate could not be verified.",
+    }
+}
+
+// A page a phone can show straight from the camera app, no scripts or external assets
+fn verdict_page(title: &str, color: &str, rows: &[(&str, String)], note: &str) -> String {
+    let rows: String = rows
+        .iter()
+        .map(|(label, value)| format!("<tr><th>{}</th><td>{}</td></tr>", label, escape_html(value)))
+        .collect();
+
+    format!(
+        r#"<!doctype html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+body {{ font-family: system-ui, sans-serif; margin: 0; padding: 1.5rem; color: #222; }}
+h1 {{ color: {color}; font-size: 1.6rem; margin: 0 0 1rem; }}
+th {{ text-align: left; padding: 0.25rem 1rem 0.25rem 0; color: #666; font-weight: normal; }}
+td {{ word-break: break-all; }}
+p {{ color: #444; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<table>{rows}</table>
+<p>{note}</p>
+</body>
+</html>
+"#,
+        title = title,
+        color = color,
+        rows = rows,
+        note = escape_html(note),
+    )
+}
+
+fn wants_html(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v/{payload}",
+    params(
+        ("payload" = String, Path, description = "Compact certificate payload from a QR code link, `ERI1.<base64url>`. Base45 `ERI1:` text is not a valid path segment, decode it with /qr_payload/decode instead")
+    ),
+    responses(
+        (status = 200, description = "The certificate and its verdict, as JSON or as an HTML page when the client accepts text/html", body = QrVerificationResult),
+        (status = 400, description = "The link holds no ERI certificate", body = ErrorResponse),
+        (status = 502, description = "RPC node unavailable", body = ErrorResponse)
+    )
+)]
+pub async fn verify_link(
+    State(state): State<AppState>,
+    Path(payload): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let html = wants_html(&headers);
+
+    let certificate = match parse_qr_text(&payload) {
+        Ok(certificate) => certificate,
+        Err(e) if html => {
+            eprintln!("Unreadable verification link: {}", e);
+            let page = verdict_page(
+                "Not a valid certificate",
+                "#b00020",
+                &[],
+                "This link does not hold an ERI certificate. It may be damaged or incomplete.",
+            );
+            return Ok((StatusCode::BAD_REQUEST, Html(page)).into_response());
+        }
+        Err(e) => return Err(e),
+    };
+
+    let result = verify_certificate(&state, &certificate).await?;
+    eprintln!("Link verification result: {:?}", result);
+
+    if !html {
+        return Ok(Json(QrVerificationResult {
+            certificate,
+            result,
+        })
+        .into_response());
+    }
+
+    let mut rows = vec![
+        ("Product", certificate.name.clone()),
+        ("Serial", certificate.serial.clone()),
+        ("ID", certificate.unique_id.clone()),
+    ];
+    let page = if result.authentic {
+        rows.push((
+            "Manufacturer",
+            result.manufacturer_name.clone().unwrap_or_default(),
+        ));
+        verdict_page(
+            "Authentic",
+            "#1b7f3b",
+            &rows,
+            "This certificate was signed by a manufacturer registered on the ERI contract.",
+        )
+    } else {
+        verdict_page(
+            "Not authentic",
+            "#b00020",
+            &rows,
+            failure_text(result.failure_reason),
+        )
+    };
+
+    Ok(Html(page).into_response())
+}
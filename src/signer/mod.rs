@@ -0,0 +1,192 @@
+pub(crate) mod registry;
+pub(crate) mod remote;
+
+use crate::config::app_config::WalletConfig;
+use anyhow::Context;
+use async_trait::async_trait;
+use eri_core::{Certificate, CertificateBatch, CertificateError, SignedCertificateBatch};
+use ethers::prelude::*;
+use ethers::types::transaction::eip712::Eip712;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use remote::RemoteSigner;
+use std::fmt;
+use std::fs;
+
+// The key the server signs certificates and sends transactions with, one variant per backend
+#[derive(Clone, Debug)]
+pub enum AppSigner {
+    /// A raw private key or a decrypted keystore, held in memory
+    Local(LocalWallet),
+    /// A JSON-RPC signer that never hands out the key
+    Remote(RemoteSigner),
+    /// No key at all, for clients that only read the chain. Every signing call fails
+    ReadOnly { chain_id: u64 },
+}
+
+#[derive(Debug)]
+pub enum SignerError {
+    Wallet(WalletError),
+    Remote(String),
+    ReadOnly,
+}
+
+impl fmt::Display for SignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignerError::Wallet(error) => write!(f, "{}", error),
+            SignerError::Remote(message) => write!(f, "remote signer: {}", message),
+            SignerError::ReadOnly => write!(f, "no wallet is loaded, this client only reads the chain"),
+        }
+    }
+}
+
+impl std::error::Error for SignerError {}
+
+impl From<WalletError> for SignerError {
+    fn from(error: WalletError) -> Self {
+        SignerError::Wallet(error)
+    }
+}
+
+impl AppSigner {
+    pub async fn from_config(config: &WalletConfig, chain_id: u64) -> anyhow::Result<AppSigner> {
+        let signer = match config {
+            WalletConfig::Key { private_key } => AppSigner::Local(
+                private_key
+                    .parse::<LocalWallet>()
+                    .context("private key is not a valid secp256k1 key")?,
+            ),
+            WalletConfig::Keystore {
+                path,
+                passphrase_file,
+            } => {
+                let passphrase = match passphrase_file {
+                    Some(file) => fs::read_to_string(file)
+                        .with_context(|| format!("failed to read passphrase file {}", file))?
+                        .trim_end_matches(['\r', '\n'])
+                        .to_string(),
+                    None => rpassword::prompt_password(format!("Passphrase for {}: ", path))
+                        .context("failed to read the keystore passphrase")?,
+                };
+                AppSigner::Local(
+                    LocalWallet::decrypt_keystore(path, passphrase)
+                        .with_context(|| format!("failed to decrypt keystore {}", path))?,
+                )
+            }
+            WalletConfig::Remote { url, address } => {
+                AppSigner::Remote(RemoteSigner::connect(url, *address, chain_id).await?)
+            }
+            WalletConfig::ReadOnly => AppSigner::ReadOnly { chain_id },
+        };
+
+        Ok(signer.with_chain_id(chain_id))
+    }
+
+    // Signs the certificate's EIP-712 digest, a remote signer gets the full typed data
+    pub async fn sign_certificate(
+        &self,
+        certificate: &Certificate,
+    ) -> Result<Signature, CertificateError> {
+        match self {
+            AppSigner::Local(wallet) => eri_core::sign_certificate(wallet, certificate).await,
+            AppSigner::Remote(remote) => {
+                let typed_data = certificate.typed_data()?;
+                remote
+                    .sign_typed_data_v4(&typed_data)
+                    .await
+                    .map_err(|e| CertificateError::Signing(e.to_string()))
+            }
+            AppSigner::ReadOnly { .. } => {
+                Err(CertificateError::Signing(SignerError::ReadOnly.to_string()))
+            }
+        }
+    }
+
+    // Signs a batch's Merkle root, the same way `sign_certificate` signs one certificate
+    pub async fn sign_batch(
+        &self,
+        batch: CertificateBatch,
+    ) -> Result<SignedCertificateBatch, CertificateError> {
+        match self {
+            AppSigner::Local(wallet) => batch.sign(wallet).await,
+            AppSigner::Remote(remote) => {
+                let signature = remote
+                    .sign_typed_data_v4(&batch.typed_data()?)
+                    .await
+                    .map_err(|e| CertificateError::Signing(e.to_string()))?;
+                Ok(SignedCertificateBatch {
+                    batch,
+                    signature: format!("0x{}", signature),
+                })
+            }
+            AppSigner::ReadOnly { .. } => {
+                Err(CertificateError::Signing(SignerError::ReadOnly.to_string()))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Signer for AppSigner {
+    type Error = SignerError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, Self::Error> {
+        match self {
+            AppSigner::Local(wallet) => Ok(wallet.sign_message(message).await?),
+            AppSigner::Remote(remote) => remote.personal_sign(message.as_ref()).await,
+            AppSigner::ReadOnly { .. } => Err(SignerError::ReadOnly),
+        }
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        match self {
+            AppSigner::Local(wallet) => Ok(wallet.sign_transaction(tx).await?),
+            AppSigner::Remote(remote) => remote.sign_transaction(tx).await,
+            AppSigner::ReadOnly { .. } => Err(SignerError::ReadOnly),
+        }
+    }
+
+    // eth_signTypedData_v4 needs the types and message, which a bare Eip712 value doesn't
+    // expose, so remote signers only sign certificates (through `sign_certificate`)
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<Signature, Self::Error> {
+        match self {
+            AppSigner::Local(wallet) => Ok(wallet.sign_typed_data(payload).await?),
+            AppSigner::Remote(_) => Err(SignerError::Remote(
+                "only certificates can be signed as typed data, use sign_certificate".to_string(),
+            )),
+            AppSigner::ReadOnly { .. } => Err(SignerError::ReadOnly),
+        }
+    }
+
+    fn address(&self) -> Address {
+        match self {
+            AppSigner::Local(wallet) => wallet.address(),
+            AppSigner::Remote(remote) => remote.address(),
+            AppSigner::ReadOnly { .. } => Address::zero(),
+        }
+    }
+
+    fn chain_id(&self) -> u64 {
+        match self {
+            AppSigner::Local(wallet) => wallet.chain_id(),
+            AppSigner::Remote(remote) => remote.chain_id(),
+            AppSigner::ReadOnly { chain_id } => *chain_id,
+        }
+    }
+
+    fn with_chain_id<T: Into<u64>>(self, chain_id: T) -> Self {
+        match self {
+            AppSigner::Local(wallet) => AppSigner::Local(wallet.with_chain_id(chain_id)),
+            AppSigner::Remote(remote) => AppSigner::Remote(remote.with_chain_id(chain_id)),
+            AppSigner::ReadOnly { .. } => AppSigner::ReadOnly {
+                chain_id: chain_id.into(),
+            },
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use super::AppSigner;
+use crate::config::app_config::ManufacturerKeyConfig;
+use crate::config::app_router::{Authenticity, EriErrorsErrors};
+use crate::config::app_state::EthClient;
+use crate::error::ApiError;
+use ethers::prelude::*;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+// Certificate signing keys by manufacturer name, as registered in Authenticity.names.
+// The server only signs for manufacturers it has a key for
+pub struct KeyRegistry {
+    keys: BTreeMap<String, AppSigner>,
+}
+
+impl KeyRegistry {
+    // Builds every configured key and checks it against the registry on chain. The server
+    // wallet is included under its own name when it is a registered manufacturer
+    pub async fn load(
+        eth_client: &Arc<EthClient>,
+        contract_address: Address,
+        chain_id: u64,
+        configured: &[ManufacturerKeyConfig],
+    ) -> anyhow::Result<KeyRegistry> {
+        let contract = Authenticity::new(contract_address, eth_client.clone());
+        let mut keys = BTreeMap::new();
+
+        for key in configured {
+            let signer = AppSigner::from_config(&key.wallet, chain_id).await?;
+
+            match contract
+                .get_manufacturer_by_name(key.manufacturer.clone())
+                .call()
+                .await
+                .map_err(ApiError::from)
+            {
+                Ok(registered) if registered != signer.address() => anyhow::bail!(
+                    "manufacturer {:?} is registered to {:?}, but its configured key is {:?}",
+                    key.manufacturer,
+                    registered,
+                    signer.address()
+                ),
+                Ok(_) => {}
+                Err(ApiError::Contract(EriErrorsErrors::DOES_NOT_EXIST(_))) => eprintln!(
+                    "⚠️ Manufacturer {:?} is not registered yet, its certificates won't verify until it is",
+                    key.manufacturer
+                ),
+                Err(e) => return Err(e.into()),
+            }
+
+            if keys.insert(key.manufacturer.clone(), signer).is_some() {
+                anyhow::bail!("more than one key configured for {:?}", key.manufacturer);
+            }
+        }
+
+        let server_signer = eth_client.signer();
+        match contract
+            .get_manufacturer(server_signer.address())
+            .call()
+            .await
+            .map_err(ApiError::from)
+        {
+            Ok(manufacturer) => {
+                keys.entry(manufacturer.name)
+                    .or_insert_with(|| server_signer.clone());
+            }
+            Err(ApiError::Contract(EriErrorsErrors::DOES_NOT_EXIST(_))) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        eprintln!("🔑 Signing keys for {} manufacturers:", keys.len());
+        for (name, signer) in &keys {
+            eprintln!("    {}: {:?}", name, signer.address());
+        }
+
+        Ok(KeyRegistry { keys })
+    }
+
+    // No keys, for clients that never sign
+    pub fn empty() -> KeyRegistry {
+        KeyRegistry {
+            keys: BTreeMap::new(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_keys(keys: impl IntoIterator<Item = (String, AppSigner)>) -> KeyRegistry {
+        KeyRegistry {
+            keys: keys.into_iter().collect(),
+        }
+    }
+
+    pub fn for_manufacturer(&self, name: &str) -> Option<&AppSigner> {
+        self.keys.get(name)
+    }
+
+    pub fn for_address(&self, address: Address) -> Option<(&str, &AppSigner)> {
+        self.keys
+            .iter()
+            .find(|(_, signer)| signer.address() == address)
+            .map(|(name, signer)| (name.as_str(), signer))
+    }
+
+    // The manufacturer and key to sign a certificate owned by `owner` with, the signer
+    // must be the owner for the certificate to verify
+    pub fn signer_for<'a>(
+        &'a self,
+        manufacturer: Option<&'a str>,
+        owner: Address,
+    ) -> Result<(&'a str, &'a AppSigner), ApiError> {
+        match manufacturer {
+            Some(name) => {
+                let signer = self.for_manufacturer(name).ok_or_else(|| {
+                    ApiError::Forbidden(format!("no signing key for manufacturer {:?}", name))
+                })?;
+                if signer.address() != owner {
+                    return Err(ApiError::bad_request(format!(
+                        "certificate owner {:?} is not {:?}'s address {:?}",
+                        owner,
+                        name,
+                        signer.address()
+                    )));
+                }
+                Ok((name, signer))
+            }
+            None => self.for_address(owner).ok_or_else(|| {
+                ApiError::Forbidden(format!("no signing key for manufacturer {:?}", owner))
+            }),
+        }
+    }
+}
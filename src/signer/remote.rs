@@ -0,0 +1,343 @@
+use super::SignerError;
+use anyhow::{Context, bail};
+use axum::routing::post;
+use axum::{Json, Router, extract::State};
+use ethers::prelude::*;
+use ethers::types::transaction::eip712::{Eip712, TypedData};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::utils::{hash_message, rlp};
+use serde_json::{Value, json};
+
+// Signs through a JSON-RPC endpoint that holds the key, e.g. web3signer, Clef or
+// `eri serve-signer`. Every signature it returns is recovered and checked against `address`
+#[derive(Clone, Debug)]
+pub struct RemoteSigner {
+    client: Http,
+    url: String,
+    address: Address,
+    chain_id: u64,
+}
+
+impl RemoteSigner {
+    // Fails unless the endpoint is reachable and lists `address` in eth_accounts
+    pub async fn connect(url: &str, address: Address, chain_id: u64) -> anyhow::Result<Self> {
+        let client = url
+            .parse::<Http>()
+            .with_context(|| format!("invalid remote signer url {:?}", url))?;
+
+        let accounts: Vec<Address> = JsonRpcClient::request(&client, "eth_accounts", ())
+            .await
+            .with_context(|| format!("could not reach the remote signer at {}", url))?;
+        if !accounts.contains(&address) {
+            bail!(
+                "remote signer at {} does not hold a key for {:?}",
+                url,
+                address
+            );
+        }
+
+        Ok(Self {
+            client,
+            url: url.to_string(),
+            address,
+            chain_id,
+        })
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    pub fn with_chain_id<T: Into<u64>>(mut self, chain_id: T) -> Self {
+        self.chain_id = chain_id.into();
+        self
+    }
+
+    async fn request<T>(&self, method: &str, params: T) -> Result<Bytes, SignerError>
+    where
+        T: serde::Serialize + Send + Sync + std::fmt::Debug,
+    {
+        JsonRpcClient::request(&self.client, method, params)
+            .await
+            .map_err(|e| SignerError::Remote(format!("{} to {} failed: {}", method, self.url, e)))
+    }
+
+    // makes sure the endpoint signed with the key we expect, not just any key
+    fn checked(&self, signature: Signature, digest: H256) -> Result<Signature, SignerError> {
+        match signature.recover(digest) {
+            Ok(signer) if signer == self.address => Ok(signature),
+            Ok(signer) => Err(SignerError::Remote(format!(
+                "signature is from {:?}, expected {:?}",
+                signer, self.address
+            ))),
+            Err(e) => Err(SignerError::Remote(e.to_string())),
+        }
+    }
+
+    pub async fn sign_typed_data_v4(&self, data: &TypedData) -> Result<Signature, SignerError> {
+        let digest = data
+            .encode_eip712()
+            .map_err(|e| SignerError::Remote(e.to_string()))?;
+        let bytes = self
+            .request("eth_signTypedData_v4", (self.address, data))
+            .await?;
+        self.checked(parse_signature(&bytes)?, H256::from(digest))
+    }
+
+    pub async fn personal_sign(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let bytes = self
+            .request(
+                "personal_sign",
+                (Bytes::from(message.to_vec()), self.address),
+            )
+            .await?;
+        self.checked(parse_signature(&bytes)?, hash_message(message))
+    }
+
+    // eth_signTransaction answers with the signed RLP, the middleware only wants the signature
+    pub async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, SignerError> {
+        let mut tx = tx.clone();
+        tx.set_from(self.address);
+        if tx.chain_id().is_none() {
+            tx.set_chain_id(self.chain_id);
+        }
+
+        // ethers never serializes chainId, but the signer needs it for the EIP-155 v
+        let mut params =
+            serde_json::to_value(&tx).map_err(|e| SignerError::Remote(e.to_string()))?;
+        params["chainId"] = json!(tx.chain_id());
+
+        let raw = self.request("eth_signTransaction", [params]).await?;
+        let (signed, signature) = TypedTransaction::decode_signed(&rlp::Rlp::new(&raw))
+            .map_err(|e| SignerError::Remote(format!("undecodable signed transaction: {}", e)))?;
+
+        // the signer may not change what we asked it to sign
+        if signed.sighash() != tx.sighash() {
+            return Err(SignerError::Remote(
+                "signed transaction differs from the request".to_string(),
+            ));
+        }
+        signature
+            .verify(tx.sighash(), self.address)
+            .map_err(|e| SignerError::Remote(e.to_string()))?;
+        Ok(signature)
+    }
+}
+
+// wallets answer with v as 0/1 or 27/28, some with compact signatures, certificates
+// carry 65 bytes with v as 27/28
+fn parse_signature(bytes: &[u8]) -> Result<Signature, SignerError> {
+    eri_core::signature_from_bytes(bytes).map_err(|e| SignerError::Remote(e.to_string()))
+}
+
+// A local stand-in for a remote signer, serving `wallet` over the same JSON-RPC methods
+pub async fn serve_stand_in(wallet: LocalWallet, listen: &str) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(listen).await?;
+    serve_stand_in_on(wallet, listener).await
+}
+
+// Same, on a listener that is already bound (port 0 in tests)
+pub(crate) async fn serve_stand_in_on(
+    wallet: LocalWallet,
+    listener: tokio::net::TcpListener,
+) -> anyhow::Result<()> {
+    let app = Router::new()
+        .route("/", post(stand_in_rpc))
+        .with_state(wallet.clone());
+
+    eprintln!(
+        "Remote signer stand-in for {:?} listening on {}",
+        wallet.address(),
+        listener.local_addr()?
+    );
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn stand_in_rpc(
+    State(wallet): State<LocalWallet>,
+    Json(request): Json<Value>,
+) -> Json<Value> {
+    let id = request["id"].clone();
+    let params = &request["params"];
+
+    let result = match request["method"].as_str().unwrap_or_default() {
+        "eth_accounts" => Ok(json!([wallet.address()])),
+        "eth_signTypedData_v4" => stand_in_typed_data(&wallet, &params[1]).await,
+        "personal_sign" => match serde_json::from_value::<Bytes>(params[0].clone()) {
+            Ok(message) => wallet
+                .sign_message(message)
+                .await
+                .map(|signature| json!(format!("0x{}", signature)))
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        },
+        "eth_signTransaction" => {
+            match serde_json::from_value::<TypedTransaction>(params[0].clone()) {
+                Ok(tx) => wallet
+                    .sign_transaction(&tx)
+                    .await
+                    .map(|signature| json!(tx.rlp_signed(&signature)))
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            }
+        }
+        method => Err(format!("method {:?} is not supported", method)),
+    };
+
+    Json(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(message) => {
+            eprintln!("Remote signer stand-in refused a request: {}", message);
+            json!({ "jsonrpc": "2.0", "id": id, "error": { "code": -32000, "message": message } })
+        }
+    })
+}
+
+// MetaMask sends the typed data as a JSON string, other clients as an object
+async fn stand_in_typed_data(wallet: &LocalWallet, data: &Value) -> Result<Value, String> {
+    let data: TypedData = match data {
+        Value::String(data) => serde_json::from_str(data),
+        data => serde_json::from_value(data.clone()),
+    }
+    .map_err(|e| e.to_string())?;
+
+    wallet
+        .sign_typed_data(&data)
+        .await
+        .map(|signature| json!(format!("0x{}", signature)))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eri_core::{Certificate, certificate_domain};
+
+    const KEY: &str = "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+    const OTHER_KEY: &str = "5de4111afa1a4b94908f83103eb1f1706367c2e68ca870fc3fb9a804cdab365a";
+
+    // a stand-in for `key` on an ephemeral port, returns its url
+    async fn stand_in(key: &str) -> String {
+        let wallet: LocalWallet = key.parse().unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve_stand_in_on(wallet, listener));
+        url
+    }
+
+    fn address(key: &str) -> Address {
+        key.parse::<LocalWallet>().unwrap().address()
+    }
+
+    fn typed_data() -> TypedData {
+        Certificate::new(
+            "Phone".to_string(),
+            "IMEI1".to_string(),
+            "SN1".to_string(),
+            1_700_000_000,
+            address(KEY),
+            vec!["red".to_string()],
+        )
+        .with_domain(certificate_domain(
+            "ERI",
+            "1",
+            31337,
+            Address::repeat_byte(0x11),
+        ))
+        .typed_data()
+        .unwrap()
+    }
+
+    fn transaction() -> TypedTransaction {
+        TransactionRequest::new()
+            .to(Address::repeat_byte(0x22))
+            .value(1_000u64)
+            .nonce(7u64)
+            .gas(21_000u64)
+            .gas_price(1_000_000_000u64)
+            .into()
+    }
+
+    fn eip1559_transaction() -> TypedTransaction {
+        Eip1559TransactionRequest::new()
+            .to(Address::repeat_byte(0x22))
+            .value(1_000u64)
+            .nonce(7u64)
+            .gas(21_000u64)
+            .max_fee_per_gas(2_000_000_000u64)
+            .max_priority_fee_per_gas(1_000_000_000u64)
+            .into()
+    }
+
+    #[tokio::test]
+    async fn signs_typed_data_through_the_stand_in() {
+        let signer = RemoteSigner::connect(&stand_in(KEY).await, address(KEY), 31337)
+            .await
+            .unwrap();
+        let data = typed_data();
+
+        let signature = signer.sign_typed_data_v4(&data).await.unwrap();
+
+        let digest = H256::from(data.encode_eip712().unwrap());
+        assert_eq!(signature.recover(digest).unwrap(), address(KEY));
+        assert!(signature.v == 27 || signature.v == 28);
+    }
+
+    #[tokio::test]
+    async fn signs_transactions_through_the_stand_in() {
+        let signer = RemoteSigner::connect(&stand_in(KEY).await, address(KEY), 31337)
+            .await
+            .unwrap();
+        let local = KEY.parse::<LocalWallet>().unwrap().with_chain_id(31337u64);
+
+        for tx in [transaction(), eip1559_transaction()] {
+            let signature = signer.sign_transaction(&tx).await.unwrap();
+
+            // signed for our chain, broadcasts the same bytes as signing locally
+            let mut expected = tx.clone();
+            expected.set_from(address(KEY));
+            expected.set_chain_id(31337u64);
+            signature.verify(expected.sighash(), address(KEY)).unwrap();
+            let signed_locally = local.sign_transaction(&expected).await.unwrap();
+            assert_eq!(
+                expected.rlp_signed(&signature),
+                expected.rlp_signed(&signed_locally)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn connect_needs_the_key_in_eth_accounts() {
+        let url = stand_in(OTHER_KEY).await;
+
+        assert!(
+            RemoteSigner::connect(&url, address(KEY), 31337)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn signature_from_another_key_is_rejected() {
+        // an endpoint that claimed our address in eth_accounts but signs with another key
+        let url = stand_in(OTHER_KEY).await;
+        let signer = RemoteSigner {
+            client: url.parse().unwrap(),
+            url,
+            address: address(KEY),
+            chain_id: 31337,
+        };
+
+        assert!(matches!(
+            signer.sign_typed_data_v4(&typed_data()).await,
+            Err(SignerError::Remote(message)) if message.contains("signature is from")
+        ));
+        assert!(signer.personal_sign(b"hello").await.is_err());
+        assert!(signer.sign_transaction(&transaction()).await.is_err());
+    }
+}
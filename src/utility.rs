@@ -1,24 +1,104 @@
-use crate::models::certificate_model::Certificate;
-use ethabi::ethereum_types::Address;
-use ethers::middleware::SignerMiddleware;
-use ethers::prelude::{Bytes, Http, LocalWallet, Provider, Signature};
+use crate::config::app_router::{Authenticity, ownership};
+use crate::error::ApiError;
+use crate::models::events::ManufacturerRegistered;
+use ethabi::RawLog;
+use ethers::contract::EthEvent;
+use ethers::middleware::Middleware;
+use ethers::abi::Token;
+use ethers::types::{Address, H256, TransactionReceipt};
 use ethers::utils::keccak256;
-use std::sync::Arc;
-// use crate::services::certificate_service::Authenticity.sol;
 
-// Convert Signature to Bytes
-pub fn to_bytes(signature: Signature) -> Bytes {
-    Bytes::from(signature.to_vec())
+// The change of ownership code for an item while `item.owner` holds it,
+// computed like the contract does: keccak256(abi.encode(item))
+pub(crate) fn item_hash(item: &ownership::Item) -> H256 {
+    let encoded = ethers::abi::encode(&[Token::Tuple(vec![
+        Token::String(item.name.clone()),
+        Token::String(item.item_id.clone()),
+        Token::String(item.serial.clone()),
+        Token::Uint(item.date),
+        Token::Address(item.owner),
+        Token::String(item.manufacturer.clone()),
+        Token::Array(item.metadata.iter().cloned().map(Token::String).collect()),
+    ])]);
+    H256::from(keccak256(&encoded))
 }
 
-pub(crate) fn to_meta_hash(metadata: &Vec<String>) -> [u8; 32] {
-    let metadata_bytes = ethers::abi::encode(&[ethers::abi::Token::Array(
-        metadata
-            .iter()
-            .map(|s| ethers::abi::Token::String(s.clone()))
-            .collect(),
-    )]);
-    keccak256(&metadata_bytes)
+// Parses a change of ownership code given as bytes32 hex
+pub(crate) fn parse_item_hash(input: &str) -> Result<H256, ApiError> {
+    input
+        .parse()
+        .map_err(|e| ApiError::bad_request(format!("Invalid item hash {:?}: {:?}", input, e)))
 }
 
-// App state to hold the project state
+// Resolves the name hash in a ManufacturerRegistered log through getManufacturer
+pub(crate) async fn resolve_manufacturer_name<M: Middleware>(
+    contract: &Authenticity<M>,
+    event: &ManufacturerRegistered,
+) -> Result<String, ApiError> {
+    let manufacturer = contract
+        .get_manufacturer(event.manufacturer_address)
+        .call()
+        .await?;
+
+    if !event.is_name(&manufacturer.name) {
+        return Err(ApiError::internal(format!(
+            "registered name of {:?} does not match the logged hash {:?}",
+            event.manufacturer_address, event.manufacturer_name_hash
+        )));
+    }
+
+    Ok(manufacturer.name)
+}
+
+// Decode the first log emitted by `contract` in a receipt that matches the given event,
+// so a same-signature event from another contract in the transaction is never picked up
+pub(crate) fn decode_receipt_event<E: EthEvent>(
+    receipt: &TransactionReceipt,
+    contract: Address,
+) -> Option<E> {
+    receipt
+        .logs
+        .iter()
+        .filter(|log| log.address == contract)
+        .find_map(|log| {
+            let raw_log = RawLog {
+                topics: log.topics.clone(),
+                data: log.data.to_vec(),
+            };
+            <E as EthEvent>::decode_log(&raw_log).ok()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::events::CodeRevoked;
+    use ethers::types::Log;
+
+    fn code_revoked_log(address: Address, item_hash: H256) -> Log {
+        Log {
+            address,
+            topics: vec![CodeRevoked::signature(), item_hash],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn decode_receipt_event_skips_logs_from_other_contracts() {
+        let ownership = Address::repeat_byte(0x22);
+        let receipt = TransactionReceipt {
+            logs: vec![
+                code_revoked_log(Address::repeat_byte(0x33), H256::repeat_byte(1)),
+                code_revoked_log(ownership, H256::repeat_byte(2)),
+            ],
+            ..Default::default()
+        };
+
+        let event: CodeRevoked = decode_receipt_event(&receipt, ownership).unwrap();
+        assert_eq!(event.item_hash, H256::repeat_byte(2));
+
+        let missing: Option<CodeRevoked> =
+            decode_receipt_event(&receipt, Address::repeat_byte(0x44));
+        assert!(missing.is_none());
+    }
+}